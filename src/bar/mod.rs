@@ -1,40 +1,183 @@
+mod status;
+
+pub use self::status::spawn_status_command;
+use crate::layout_manager::Geometry;
 use crate::oscillator::Oscillator;
+use crate::setting::BarPosition;
 use crate::setting::Settings;
 use crate::utils::color::Color;
+use crate::utils::strut::PartialStrut;
+use crate::utils::strut::Strut;
+use std::cell::Cell;
 use std::cell::RefCell;
 use std::collections::HashSet;
 use std::sync::Arc;
 
+// Parts of the bar which can be clicked
+pub enum BarRegion {
+    Tag(u32),
+    Title,
+    Status,
+}
+
+// Every monitor has its own bar in an override-redirect dock window at its top or bottom
 pub struct Bar {
     settings: Arc<Settings>,
+    monitor: usize,
+    window: u32,
+    shown: Cell<bool>,
+    x: u32,
+    y: u32,
     width: u32,
     height: u32,
     font: u32,
+    // Graphics context with the font, colors are changed before every drawing
+    gc: u32,
     current_tag: Arc<RefCell<HashSet<u32>>>,
+    // Where the status starts since the last draw
+    status_x: Cell<u32>,
 }
 
 impl Bar {
     pub fn new(
         settings: Arc<Settings>,
-        width: u32,
+        monitor: usize,
+        geometry: Geometry,
         current_tag: Arc<RefCell<HashSet<u32>>>,
     ) -> Bar {
-        let bar_height = settings.get_bar().height;
+        let bar_height = settings.get_bar().height.min(geometry.height);
+        let y = match settings.get_bar().position {
+            BarPosition::Top => geometry.y,
+            BarPosition::Bottom => geometry.y + geometry.height - bar_height,
+        };
         Bar {
             settings,
-            width,
+            monitor,
+            window: 0,
+            shown: Cell::new(true),
+            x: geometry.x,
+            y,
+            width: geometry.width,
             height: bar_height,
             font: 0,
+            gc: 0,
             current_tag,
+            status_x: Cell::new(geometry.width),
+        }
+    }
+
+    pub fn get_monitor(&self) -> usize {
+        self.monitor
+    }
+
+    pub fn get_window(&self) -> u32 {
+        self.window
+    }
+
+    pub fn is_shown(&self) -> bool {
+        self.shown.get()
+    }
+
+    // Space taken from the monitor
+    pub fn reserved(&self) -> Strut {
+        if !self.shown.get() {
+            return Strut::default();
+        }
+        match self.settings.get_bar().position {
+            BarPosition::Top => Strut {
+                top: self.height,
+                ..Strut::default()
+            },
+            BarPosition::Bottom => Strut {
+                bottom: self.height,
+                ..Strut::default()
+            },
+        }
+    }
+
+    // The reservation published in _NET_WM_STRUT_PARTIAL, relative to the screen edges
+    pub fn partial_strut(&self, screen_height: u32) -> PartialStrut {
+        let mut strut = PartialStrut::full(Strut::default());
+        if self.shown.get() {
+            let start = self.x;
+            let end = self.x + self.width - 1;
+            match self.settings.get_bar().position {
+                BarPosition::Top => {
+                    strut.strut.top = self.y + self.height;
+                    strut.top_start_x = start;
+                    strut.top_end_x = end;
+                }
+                BarPosition::Bottom => {
+                    strut.strut.bottom = screen_height.saturating_sub(self.y);
+                    strut.bottom_start_x = start;
+                    strut.bottom_end_x = end;
+                }
+            }
+        }
+        strut
+    }
+
+    pub fn set_shown(&self, root: &Oscillator, shown: bool) {
+        self.shown.set(shown);
+        if shown {
+            xcb::map_window(&root.connection, self.window);
+            self.draw(root);
+        } else {
+            xcb::unmap_window(&root.connection, self.window);
+        }
+        root.set_window_strut(self.window, &self.partial_strut(root.get_screen_height()));
+    }
+
+    // Keep the bar of a monitor hidden when it is recreated
+    pub fn hide_on_prepare(&mut self, hidden: bool) {
+        self.shown.set(!hidden);
+    }
+
+    pub fn destroy(&self, root: &Oscillator) {
+        xcb::destroy_window(&root.connection, self.window);
+        xcb::free_gc(&root.connection, self.gc);
+        xcb::close_font(&root.connection, self.font);
+    }
+
+    // The region under a point of the screen, None if it is outside of the bar
+    pub fn region_at(&self, x: i32, y: i32) -> Option<BarRegion> {
+        if !self.shown.get()
+            || x < self.x as i32
+            || x >= (self.x + self.width) as i32
+            || y < self.y as i32
+            || y >= (self.y + self.height) as i32
+        {
+            return None;
+        }
+
+        let x = x as u32 - self.x;
+        let cell_width = self.settings.get_bar().tag_cell_width;
+        if x < 10 * cell_width {
+            Some(BarRegion::Tag(x / cell_width))
+        } else if x >= self.status_x.get() {
+            Some(BarRegion::Status)
+        } else {
+            Some(BarRegion::Title)
         }
     }
 
     pub fn prepare(&mut self, root: &Oscillator) {
         self.font = root.create_font(&self.settings.get_bar().font_family);
+        self.window = root.create_dock_window(
+            Geometry::new(self.x, self.y, self.width, self.height),
+            Color::from(&self.settings.get_bar().background_color),
+        );
+        self.gc = root.create_gc(self.window, self.font);
+        self.set_shown(root, self.shown.get());
     }
 
     pub fn draw(&self, root: &Oscillator) {
+        if !self.shown.get() {
+            return;
+        }
         root.fill_rect(
+            self.window,
+            self.gc,
             0,
             0,
             self.width as i32,
@@ -43,6 +186,7 @@ impl Bar {
         );
 
         let cell_width = self.settings.get_bar().tag_cell_width;
+        let urgent_tags = root.get_urgent_tags(self.monitor);
         // TODO: tags range in settings
         for i in 0..10 {
             let s = format!("{}", i);
@@ -55,6 +199,8 @@ impl Bar {
 
             if self.current_tag.borrow().contains(&i) {
                 root.fill_rect(
+                    self.window,
+                    self.gc,
                     (i * cell_width) as i32,
                     0,
                     cell_width as i32,
@@ -62,23 +208,136 @@ impl Bar {
                     Color::from(&self.settings.get_bar().active_background_color),
                 );
                 root.draw_text(
+                    self.window,
+                    self.gc,
                     x as i32,
                     y as i32,
                     Color::from(&self.settings.get_bar().active_font_color),
                     Color::from(&self.settings.get_bar().active_background_color),
-                    self.font,
+                    &s,
+                );
+            } else if urgent_tags.contains(&i) {
+                root.fill_rect(
+                    self.window,
+                    self.gc,
+                    (i * cell_width) as i32,
+                    0,
+                    cell_width as i32,
+                    self.height as i32,
+                    Color::from(&self.settings.get_bar().urgent_background_color),
+                );
+                root.draw_text(
+                    self.window,
+                    self.gc,
+                    x as i32,
+                    y as i32,
+                    Color::from(&self.settings.get_bar().urgent_font_color),
+                    Color::from(&self.settings.get_bar().urgent_background_color),
                     &s,
                 );
             } else {
                 root.draw_text(
+                    self.window,
+                    self.gc,
                     x as i32,
                     y as i32,
                     Color::from(&self.settings.get_bar().font_color),
                     Color::from(&self.settings.get_bar().background_color),
-                    self.font,
                     &s,
                 );
             }
         }
+
+        let status_x = self.draw_status(root, 10 * cell_width);
+        self.status_x.set(status_x);
+        self.draw_title(root, 10 * cell_width, status_x);
+    }
+
+    // Status at the right end of the bar, return where it starts
+    fn draw_status(&self, root: &Oscillator, x: u32) -> u32 {
+        let padding = self.settings.get_bar().tag_cell_width / 2;
+        if self.width <= x + 2 * padding {
+            return self.width;
+        }
+        let status = self.truncate(root, &root.get_status(), self.width - x - 2 * padding);
+        if status.is_empty() {
+            return self.width;
+        }
+
+        let text_extends = root.query_text_extents(self.font, &status);
+        let status_x = self.width - padding - text_extends.overall_width as u32;
+        let y = self.height / 2
+            + ((text_extends.font_ascent + text_extends.font_descent) / 2
+                - text_extends.font_descent) as u32;
+        root.draw_text(
+            self.window,
+            self.gc,
+            status_x as i32,
+            y as i32,
+            Color::from(&self.settings.get_bar().font_color),
+            Color::from(&self.settings.get_bar().background_color),
+            &status,
+        );
+        status_x - padding
+    }
+
+    // Title of the focused window between the tags and the status
+    fn draw_title(&self, root: &Oscillator, x: u32, end: u32) {
+        let padding = self.settings.get_bar().tag_cell_width / 2;
+        if end <= x + 2 * padding {
+            return;
+        }
+        let max_width = end - x - 2 * padding;
+
+        let title = match root.get_focused_title(self.monitor) {
+            Some(title) => self.truncate(root, &title, max_width),
+            None => return,
+        };
+        if title.is_empty() {
+            return;
+        }
+
+        let text_extends = root.query_text_extents(self.font, &title);
+        let y = self.height / 2
+            + ((text_extends.font_ascent + text_extends.font_descent) / 2
+                - text_extends.font_descent) as u32;
+        root.draw_text(
+            self.window,
+            self.gc,
+            (x + padding) as i32,
+            y as i32,
+            Color::from(&self.settings.get_bar().font_color),
+            Color::from(&self.settings.get_bar().background_color),
+            &title,
+        );
+    }
+
+    // Cut the text and append an ellipsis until it fits in `max_width`
+    fn truncate(&self, root: &Oscillator, text: &str, max_width: u32) -> String {
+        // image_text_8 can draw at most 255 bytes
+        let fits = |s: &str| {
+            s.len() <= 255
+                && root.query_text_extents(self.font, s).overall_width as u32 <= max_width
+        };
+        if fits(text) {
+            return text.to_string();
+        }
+
+        // Binary search the longest prefix ending on a char boundary which still fits
+        let truncated = |end: usize| format!("{}...", text[..end].trim_end());
+        let ends: Vec<usize> = text.char_indices().map(|(index, _)| index).collect();
+        if !fits(&truncated(0)) {
+            return String::new();
+        }
+        let (mut low, mut high) = (0, ends.len() - 1);
+        while low < high {
+            let middle = (low + high).div_ceil(2);
+            if fits(&truncated(ends[middle])) {
+                low = middle;
+            } else {
+                high = middle - 1;
+            }
+        }
+        truncated(ends[low])
     }
 }
@@ -0,0 +1,66 @@
+use std::io::BufRead;
+use std::io::BufReader;
+use std::process::Command;
+use std::process::Stdio;
+use std::sync::mpsc;
+use std::sync::mpsc::Receiver;
+use std::thread;
+
+// Run the status command in another thread. Every line it prints is sent through the channel,
+// then a _OSCILLATOR_STATUS client message to the root window wakes up the main loop.
+pub fn spawn_status_command(command: &[String], root: u32) -> Option<Receiver<String>> {
+    if command.is_empty() {
+        return None;
+    }
+    info!("Spawn status command: \"{}\"", command.join(" "));
+    let mut child = match Command::new(&command[0])
+        .args(&command[1..])
+        .stdout(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            warn!("Spawn status command failed: {}", e);
+            return None;
+        }
+    };
+    let stdout = child.stdout.take()?;
+
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let (connection, _) = match xcb::Connection::connect(None) {
+            Ok(connection) => connection,
+            Err(_) => {
+                warn!("Status thread cannot connect to X server");
+                return;
+            }
+        };
+        let atom = match xcb::intern_atom(&connection, false, "_OSCILLATOR_STATUS").get_reply() {
+            Ok(reply) => reply.atom(),
+            Err(_) => return,
+        };
+
+        for line in BufReader::new(stdout).lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => break,
+            };
+            if sender.send(line).is_err() {
+                break;
+            }
+            let data = xcb::ClientMessageData::from_data32([0, 0, 0, 0, 0]);
+            let event = xcb::ClientMessageEvent::new(32, root, atom, data);
+            xcb::send_event(
+                &connection,
+                false,
+                root,
+                xcb::EVENT_MASK_SUBSTRUCTURE_REDIRECT,
+                &event,
+            );
+            connection.flush();
+        }
+        warn!("Status command exited");
+        let _ = child.wait();
+    });
+    Some(receiver)
+}
@@ -0,0 +1,47 @@
+use crate::keyboard::keymod::MOD_MAP;
+use crate::keyboard::keysymdef::KEYSYM_MAP;
+
+pub struct KeyBind {
+    pub modifiers: u16,
+    pub keysyms: Vec<u32>,
+}
+
+// Turn a modifier name in settings (e.g. "M4") into its mask
+pub fn parse_modifier(modifier: &str) -> Option<u16> {
+    MOD_MAP
+        .entries()
+        .find(|(_, name)| name.to_lowercase() == modifier.to_lowercase())
+        .map(|(mask, _)| *mask as u16)
+}
+
+// Look up the keysym with exactly the given name (e.g. "Num_Lock")
+pub fn keysym_by_name(name: &str) -> Option<u32> {
+    KEYSYM_MAP
+        .entries()
+        .find(|(_, keysym_name)| **keysym_name == name)
+        .map(|(keysym, _)| *keysym)
+}
+
+// Turn a key string in settings (e.g. "M4-S-Return") back into modifiers and keysyms.
+// Key strings are matched case-insensitively, so "a" gives both XK_a and XK_A.
+pub fn parse_key_string(key_string: &str) -> Option<KeyBind> {
+    let key_string = key_string.to_lowercase();
+    let mut parts: Vec<&str> = key_string.split('-').collect();
+    let key_name = parts.pop()?;
+
+    let mut modifiers = 0u16;
+    for part in parts {
+        modifiers |= parse_modifier(part)?;
+    }
+
+    let keysyms: Vec<u32> = KEYSYM_MAP
+        .entries()
+        .filter(|(_, name)| name.to_lowercase() == key_name)
+        .map(|(keysym, _)| *keysym)
+        .collect();
+    if keysyms.is_empty() {
+        return None;
+    }
+
+    Some(KeyBind { modifiers, keysyms })
+}
@@ -1,2 +1,3 @@
+pub mod keybind;
 pub mod keymod;
 pub mod keysymdef;
@@ -0,0 +1,259 @@
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Geometry {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Geometry {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Geometry {
+        Geometry {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    // Cut the area into `count` rows of (almost) the same height
+    pub fn split_rows(&self, count: usize) -> Vec<Geometry> {
+        let count = count as u32;
+        (0..count)
+            .map(|i| {
+                let y = self.y + self.height * i / count;
+                let next_y = self.y + self.height * (i + 1) / count;
+                Geometry::new(self.x, y, self.width, next_y - y)
+            })
+            .collect()
+    }
+
+    // Cut the area into `count` columns of (almost) the same width
+    pub fn split_columns(&self, count: usize) -> Vec<Geometry> {
+        let count = count as u32;
+        (0..count)
+            .map(|i| {
+                let x = self.x + self.width * i / count;
+                let next_x = self.x + self.width * (i + 1) / count;
+                Geometry::new(x, self.y, next_x - x, self.height)
+            })
+            .collect()
+    }
+
+    // Cut a column of `width` from the left side, returning it and the rest
+    pub fn cut_left(&self, width: u32) -> (Geometry, Geometry) {
+        let width = width.min(self.width);
+        (
+            Geometry::new(self.x, self.y, width, self.height),
+            Geometry::new(self.x + width, self.y, self.width - width, self.height),
+        )
+    }
+
+    // Cut a row of `height` from the top side, returning it and the rest
+    pub fn cut_top(&self, height: u32) -> (Geometry, Geometry) {
+        let height = height.min(self.height);
+        (
+            Geometry::new(self.x, self.y, self.width, height),
+            Geometry::new(self.x, self.y + height, self.width, self.height - height),
+        )
+    }
+}
+
+pub trait Layout {
+    fn name(&self) -> &'static str;
+
+    // Calculate the geometry (including border) of each window, in the order of `windows`
+    fn arrange(
+        &self,
+        windows: &[u32],
+        area: Geometry,
+        master_ratio: f32,
+        master_count: u32,
+    ) -> Vec<Geometry>;
+}
+
+pub fn layout_from_name(name: &str) -> Option<Box<dyn Layout>> {
+    match name.to_lowercase().as_str() {
+        "stack" => Some(Box::new(Stack)),
+        "monocle" => Some(Box::new(Monocle)),
+        "grid" => Some(Box::new(Grid)),
+        "columns" => Some(Box::new(Columns)),
+        "spiral" | "fibonacci" => Some(Box::new(Spiral)),
+        "centered_master" => Some(Box::new(CenteredMaster)),
+        _ => None,
+    }
+}
+
+// Master windows on the left, the others stacked on the right
+pub struct Stack;
+
+impl Layout for Stack {
+    fn name(&self) -> &'static str {
+        "stack"
+    }
+
+    fn arrange(
+        &self,
+        windows: &[u32],
+        area: Geometry,
+        master_ratio: f32,
+        master_count: u32,
+    ) -> Vec<Geometry> {
+        let master_count = (master_count as usize).min(windows.len());
+        let stack_count = windows.len() - master_count;
+
+        if master_count == 0 {
+            return area.split_rows(stack_count);
+        }
+        if stack_count == 0 {
+            return area.split_rows(master_count);
+        }
+
+        let (master, stack) = area.cut_left((area.width as f32 * master_ratio) as u32);
+        let mut geometries = master.split_rows(master_count);
+        geometries.append(&mut stack.split_rows(stack_count));
+        geometries
+    }
+}
+
+// Every window takes the whole area
+pub struct Monocle;
+
+impl Layout for Monocle {
+    fn name(&self) -> &'static str {
+        "monocle"
+    }
+
+    fn arrange(&self, windows: &[u32], area: Geometry, _: f32, _: u32) -> Vec<Geometry> {
+        windows.iter().map(|_| area).collect()
+    }
+}
+
+// Windows in a grid without gaps, later columns get the extra rows
+pub struct Grid;
+
+impl Layout for Grid {
+    fn name(&self) -> &'static str {
+        "grid"
+    }
+
+    fn arrange(&self, windows: &[u32], area: Geometry, _: f32, _: u32) -> Vec<Geometry> {
+        let count = windows.len();
+        if count == 0 {
+            return Vec::new();
+        }
+
+        let mut columns = 1;
+        while columns * columns < count {
+            columns += 1;
+        }
+
+        let mut geometries = Vec::new();
+        for (index, column) in area.split_columns(columns).iter().enumerate() {
+            let rows = count / columns
+                + if index >= columns - count % columns {
+                    1
+                } else {
+                    0
+                };
+            geometries.append(&mut column.split_rows(rows));
+        }
+        geometries
+    }
+}
+
+// All windows side by side with the same width
+pub struct Columns;
+
+impl Layout for Columns {
+    fn name(&self) -> &'static str {
+        "columns"
+    }
+
+    fn arrange(&self, windows: &[u32], area: Geometry, _: f32, _: u32) -> Vec<Geometry> {
+        area.split_columns(windows.len())
+    }
+}
+
+// Each window takes a part of the remaining area, turning clockwise
+pub struct Spiral;
+
+impl Layout for Spiral {
+    fn name(&self) -> &'static str {
+        "spiral"
+    }
+
+    fn arrange(&self, windows: &[u32], area: Geometry, master_ratio: f32, _: u32) -> Vec<Geometry> {
+        let mut geometries = Vec::new();
+        let mut rest = area;
+        for index in 0..windows.len() {
+            if index == windows.len() - 1 {
+                geometries.push(rest);
+                break;
+            }
+
+            let ratio = if index == 0 { master_ratio } else { 0.5 };
+            let width = (rest.width as f32 * ratio) as u32;
+            let height = (rest.height as f32 * ratio) as u32;
+            let (window, next) = match index % 4 {
+                0 => rest.cut_left(width),
+                1 => rest.cut_top(height),
+                2 => {
+                    let (next, window) = rest.cut_left(rest.width - width);
+                    (window, next)
+                }
+                _ => {
+                    let (next, window) = rest.cut_top(rest.height - height);
+                    (window, next)
+                }
+            };
+            geometries.push(window);
+            rest = next;
+        }
+        geometries
+    }
+}
+
+// Master windows in the middle, the others alternately on the left and the right
+pub struct CenteredMaster;
+
+impl Layout for CenteredMaster {
+    fn name(&self) -> &'static str {
+        "centered_master"
+    }
+
+    fn arrange(
+        &self,
+        windows: &[u32],
+        area: Geometry,
+        master_ratio: f32,
+        master_count: u32,
+    ) -> Vec<Geometry> {
+        let master_count = (master_count as usize).min(windows.len());
+        let stack_count = windows.len() - master_count;
+
+        if master_count == 0 || stack_count <= 1 {
+            return Stack.arrange(windows, area, master_ratio, master_count as u32);
+        }
+
+        let master_width = (area.width as f32 * master_ratio) as u32;
+        let side_width = (area.width - master_width) / 2;
+        let (left, rest) = area.cut_left(side_width);
+        let (master, right) = rest.cut_left(master_width);
+
+        let right_count = stack_count / 2;
+        let left_count = stack_count - right_count;
+        let mut left_geometries = left.split_rows(left_count).into_iter();
+        let mut right_geometries = right.split_rows(right_count).into_iter();
+
+        let mut geometries = master.split_rows(master_count);
+        for index in 0..stack_count {
+            if index % 2 == 0 {
+                geometries.push(left_geometries.next().unwrap());
+            } else {
+                geometries.push(right_geometries.next().unwrap());
+            }
+        }
+        geometries
+    }
+}
@@ -1,7 +1,16 @@
+mod layout;
+mod monitor;
+
+pub use self::layout::Geometry;
+use self::layout::*;
+use self::monitor::Monitor;
 use crate::oscillator::Oscillator;
 use crate::setting::*;
 use crate::utils::color::Color;
+use crate::utils::size_hints::SizeHints;
+use crate::utils::strut::Strut;
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::collections::HashSet;
 use std::sync::Arc;
 
@@ -9,7 +18,18 @@ pub struct Window {
     // INPUT:
     window_id: u32,
     focused: bool,
+    floating: bool,
     tags: HashSet<u32>,
+    monitor: usize,
+    size_hints: SizeHints,
+    // Sequence number of the time it became urgent
+    urgent: Option<u64>,
+    fullscreen: bool,
+    transient_for: Option<u32>,
+    // Border width set by rules
+    border_override: Option<u32>,
+    // Floating geometry to restore when leaving fullscreen
+    saved_geometry: Option<Geometry>,
 
     // OUTPUT:
     width: u32,
@@ -21,100 +41,375 @@ pub struct Window {
     border_color: Color,
 }
 
+// Layout state remembered for every tag
+pub struct TagState {
+    layout: usize,
+    master_ratio: f32,
+    master_count: u32,
+    // Window ids of this tag in tiling order
+    order: Vec<u32>,
+}
+
 pub struct LayoutManager {
     windows: Vec<Window>,
     settings: Arc<Settings>,
-    width: u32,
-    height: u32,
-    current_tag: Arc<RefCell<HashSet<u32>>>,
+    monitors: Vec<Monitor>,
+    focused_monitor: usize,
+    layouts: Vec<Box<dyn Layout>>,
+    // Tags of every monitor have their own state
+    tag_states: HashMap<(usize, u32), TagState>,
+    urgent_sequence: u64,
 }
 
 impl LayoutManager {
-    pub fn new(
-        settings: Arc<Settings>,
-        width: u32,
-        height: u32,
-        current_tag: Arc<RefCell<HashSet<u32>>>,
-    ) -> LayoutManager {
+    pub fn new(settings: Arc<Settings>, monitors: &[Geometry]) -> LayoutManager {
+        let mut layouts = Vec::new();
+        for name in &settings.get_layout_manager_settings().layouts {
+            match layout_from_name(name) {
+                Some(layout) => layouts.push(layout),
+                None => warn!("Unknown layout: {}", name),
+            }
+        }
+        if layouts.is_empty() {
+            layouts.push(Box::new(Stack));
+        }
+
         LayoutManager {
             windows: Vec::new(),
             settings,
-            width,
-            height,
-            current_tag,
+            monitors: monitors
+                .iter()
+                .map(|geometry| Monitor::new(*geometry))
+                .collect(),
+            focused_monitor: 0,
+            layouts,
+            tag_states: HashMap::new(),
+            urgent_sequence: 0,
         }
     }
 
     fn is_window_of_index_in_current_tag(&self, index: usize) -> bool {
-        self.current_tag
+        let window = &self.windows[index];
+        self.monitors[window.monitor]
+            .current_tag
             .borrow()
-            .intersection(&self.windows[index].tags)
+            .intersection(&window.tags)
             .into_iter()
             .peekable()
             .peek()
             != None
     }
 
-    pub fn recalc(&mut self) {
-        match self.settings.get_layout_manager_settings() {
-            LayoutManagerSettings::Stack {
-                border,
-                focus_border_color,
-                normal_border_color,
-            } => {
-                let mut length = 0u32;
-                let mut mapped_window_index = Vec::new();
-
-                for index in 0..self.windows.len() {
-                    if self.is_window_of_index_in_current_tag(index) {
-                        mapped_window_index.push(index);
-                        length += 1;
-                        self.windows[index].mapped = true;
-                    } else {
-                        self.windows[index].mapped = false;
-                    }
-                }
+    // When several tags are selected, the one with the lowest number decides the layout
+    pub fn primary_tag(&self, monitor: usize) -> u32 {
+        self.monitors[monitor]
+            .current_tag
+            .borrow()
+            .iter()
+            .cloned()
+            .min()
+            .unwrap_or(0)
+    }
 
-                if length == 1 {
-                    let index_zero = mapped_window_index[0];
-                    self.windows[index_zero].x = 0;
-                    self.windows[index_zero].y = self.settings.get_bar().height;
-                    self.windows[index_zero].width = self.width - 2 * *border;
-                    self.windows[index_zero].height =
-                        self.height - self.settings.get_bar().height - 2 * *border;
-                } else if length > 1 {
-                    let index_zero = mapped_window_index[0];
-                    self.windows[index_zero].x = 0;
-                    self.windows[index_zero].y = self.settings.get_bar().height;
-                    self.windows[index_zero].width = self.width / 2 - 2 * border;
-                    self.windows[index_zero].height =
-                        self.height - self.settings.get_bar().height - 2 * border;
-                }
+    fn tag_state(&mut self, monitor: usize, tag: u32) -> &mut TagState {
+        let settings = self.settings.get_layout_manager_settings();
+        self.tag_states
+            .entry((monitor, tag))
+            .or_insert_with(|| TagState {
+                layout: 0,
+                master_ratio: settings.master_ratio,
+                master_count: settings.master_count,
+                order: Vec::new(),
+            })
+    }
+
+    fn current_tag_state(&mut self, monitor: usize) -> &mut TagState {
+        let tag = self.primary_tag(monitor);
+        self.tag_state(monitor, tag)
+    }
+
+    fn add_window_to_tag(&mut self, window_id: u32, monitor: usize, tag: u32) {
+        let order = &mut self.tag_state(monitor, tag).order;
+        if !order.contains(&window_id) {
+            order.push(window_id);
+        }
+    }
+
+    fn remove_window_from_tags(&mut self, window_id: u32) {
+        for state in self.tag_states.values_mut() {
+            state.order.retain(|id| *id != window_id);
+        }
+    }
+
+    pub fn inc_master_ratio(&mut self, delta: f32) {
+        let state = self.current_tag_state(self.focused_monitor);
+        state.master_ratio = (state.master_ratio + delta).clamp(0.05, 0.95);
+        info!("Set master ratio to {}", state.master_ratio);
+    }
+
+    pub fn inc_master_count(&mut self, delta: i32) {
+        let state = self.current_tag_state(self.focused_monitor);
+        state.master_count = (state.master_count as i32 + delta).max(0) as u32;
+        info!("Set master count to {}", state.master_count);
+    }
 
-                let item_height = if length > 1 {
-                    (self.height - self.settings.get_bar().height) / (length - 1) - 2 * border
-                } else {
-                    self.height - self.settings.get_bar().height - 2 * border
-                };
-                for i in 1..(length as usize) {
-                    let index = mapped_window_index[i];
-                    println!("YEAHYEAHYEAH");
-                    self.windows[index].x = self.width / 2;
-                    self.windows[index].y = (item_height + 2 * border) * ((i - 1) as u32)
-                        + self.settings.get_bar().height;
-                    self.windows[index].height = item_height;
-                    self.windows[index].width = self.width / 2 - 2 * border;
+    pub fn next_layout(&mut self) {
+        let layout_count = self.layouts.len();
+        let state = self.current_tag_state(self.focused_monitor);
+        state.layout = (state.layout + 1) % layout_count;
+        let layout = state.layout;
+        info!("Set layout to {}", self.layouts[layout].name());
+    }
+
+    pub fn set_layout(&mut self, name: &str) {
+        let name = match layout_from_name(name) {
+            Some(layout) => layout.name(),
+            None => {
+                warn!("Unknown layout: {}", name);
+                return;
+            }
+        };
+        match self.layouts.iter().position(|layout| layout.name() == name) {
+            Some(layout) => {
+                self.current_tag_state(self.focused_monitor).layout = layout;
+                info!("Set layout to {}", name);
+            }
+            None => warn!("Layout {} is not enabled", name),
+        }
+    }
+
+    pub fn monitor_count(&self) -> usize {
+        self.monitors.len()
+    }
+
+    pub fn get_focused_monitor(&self) -> usize {
+        self.focused_monitor
+    }
+
+    pub fn focus_monitor(&mut self, monitor: usize) {
+        if monitor < self.monitors.len() {
+            self.focused_monitor = monitor;
+        }
+    }
+
+    pub fn get_monitor_geometries(&self) -> Vec<Geometry> {
+        self.monitors
+            .iter()
+            .map(|monitor| monitor.geometry)
+            .collect()
+    }
+
+    // Replace the monitors after a RandR change. Monitors at the same position keep their
+    // tags, the others are reused in order. Windows of removed monitors move to the first one.
+    pub fn set_monitors(&mut self, geometries: &[Geometry]) {
+        let old_monitors: Vec<Monitor> = self.monitors.drain(..).collect();
+        let mut new_from_old: Vec<Option<usize>> = geometries
+            .iter()
+            .map(|geometry| {
+                old_monitors.iter().position(|monitor| {
+                    monitor.geometry.x == geometry.x && monitor.geometry.y == geometry.y
+                })
+            })
+            .collect();
+        for index in 0..geometries.len() {
+            if new_from_old[index].is_none() {
+                new_from_old[index] =
+                    (0..old_monitors.len()).find(|old| !new_from_old.contains(&Some(*old)));
+            }
+        }
+        let old_to_new: Vec<Option<usize>> = (0..old_monitors.len())
+            .map(|old| new_from_old.iter().position(|new| *new == Some(old)))
+            .collect();
+
+        let mut old_monitors: Vec<Option<Monitor>> = old_monitors.into_iter().map(Some).collect();
+        for (index, geometry) in geometries.iter().enumerate() {
+            let monitor = match new_from_old[index] {
+                Some(old) => {
+                    let mut monitor = old_monitors[old].take().unwrap();
+                    monitor.geometry = *geometry;
+                    monitor
                 }
+                None => Monitor::new(*geometry),
+            };
+            self.monitors.push(monitor);
+        }
+
+        let tag_states = std::mem::take(&mut self.tag_states);
+        for ((old, tag), state) in tag_states {
+            if let Some(Some(new)) = old_to_new.get(old) {
+                self.tag_states.insert((*new, tag), state);
+            }
+        }
 
-                for window in &mut self.windows {
-                    window.border = *border;
-                    if window.focused {
-                        window.border_color = Color::from(focus_border_color)
-                    } else {
-                        window.border_color = Color::from(normal_border_color)
-                    }
+        let mut moved = Vec::new();
+        for window in &mut self.windows {
+            match old_to_new.get(window.monitor) {
+                Some(Some(new)) => window.monitor = *new,
+                _ => {
+                    window.monitor = 0;
+                    moved.push((window.window_id, window.tags.clone()));
                 }
             }
+            // Keep floating windows inside of their monitor
+            if window.floating {
+                let geometry = self.monitors[window.monitor].geometry;
+                window.x = window.x.clamp(
+                    geometry.x,
+                    geometry.x + geometry.width.saturating_sub(window.width),
+                );
+                window.y = window.y.clamp(
+                    geometry.y,
+                    geometry.y + geometry.height.saturating_sub(window.height),
+                );
+            }
+        }
+        for (window_id, tags) in moved {
+            info!("Move window {} of removed monitor to monitor 0", window_id);
+            for tag in tags {
+                self.add_window_to_tag(window_id, 0, tag);
+            }
+        }
+
+        self.focused_monitor = old_to_new
+            .get(self.focused_monitor)
+            .cloned()
+            .flatten()
+            .unwrap_or(0);
+    }
+
+    pub fn get_monitor_geometry(&self, monitor: usize) -> Geometry {
+        self.monitors[monitor].geometry
+    }
+
+    pub fn get_current_tag(&self, monitor: usize) -> Arc<RefCell<HashSet<u32>>> {
+        self.monitors[monitor].current_tag.clone()
+    }
+
+    // The monitor containing the point, or the focused one if it is outside of all monitors
+    pub fn monitor_at(&self, x: u32, y: u32) -> usize {
+        self.monitors
+            .iter()
+            .position(|monitor| monitor.contains(x, y))
+            .unwrap_or(self.focused_monitor)
+    }
+
+    pub fn set_strut(&mut self, monitor: usize, strut: Strut) {
+        self.monitors[monitor].strut = strut;
+    }
+
+    pub fn set_bar_strut(&mut self, monitor: usize, strut: Strut) {
+        self.monitors[monitor].bar = strut;
+    }
+
+    // The monitor without the bar and the space reserved by docks
+    pub fn tiling_area(&self, monitor: usize) -> Geometry {
+        let monitor = &self.monitors[monitor];
+        let strut = monitor.bar.merge(&monitor.strut);
+        let geometry = monitor.geometry;
+        let width = geometry
+            .width
+            .saturating_sub(strut.left + strut.right)
+            .max(1);
+        let height = geometry
+            .height
+            .saturating_sub(strut.top + strut.bottom)
+            .max(1);
+        Geometry::new(
+            geometry.x + strut.left,
+            geometry.y + strut.top,
+            width,
+            height,
+        )
+    }
+
+    // Float the window with the given size at the center of `parent`
+    pub fn center_floating(&mut self, window_id: u32, parent: Geometry, width: u32, height: u32) {
+        let x = parent.x as i32 + (parent.width as i32 - width as i32) / 2;
+        let y = parent.y as i32 + (parent.height as i32 - height as i32) / 2;
+        self.set_floating_geometry(
+            window_id,
+            Geometry::new(x.max(0) as u32, y.max(0) as u32, width, height),
+        );
+    }
+
+    pub fn recalc(&mut self) {
+        let settings = self.settings.clone();
+        let layout_settings = settings.get_layout_manager_settings();
+        let border = layout_settings.border;
+
+        for index in 0..self.windows.len() {
+            self.windows[index].mapped = self.is_window_of_index_in_current_tag(index);
+        }
+        for monitor in 0..self.monitors.len() {
+            self.arrange_monitor(monitor, border);
+        }
+
+        for window in &mut self.windows {
+            if window.fullscreen {
+                let geometry = self.monitors[window.monitor].geometry;
+                window.x = geometry.x;
+                window.y = geometry.y;
+                window.width = geometry.width;
+                window.height = geometry.height;
+                window.border = 0;
+            } else {
+                window.border = window.border_override.unwrap_or(border);
+            }
+            if window.focused {
+                window.border_color = Color::from(&layout_settings.focus_border_color)
+            } else {
+                window.border_color = Color::from(&layout_settings.normal_border_color)
+            }
+        }
+    }
+
+    // Tile the shown windows of the monitor
+    fn arrange_monitor(&mut self, monitor: usize, border: u32) {
+        // Floating and fullscreen windows are not tiled
+        let mut mapped_window_index: Vec<usize> = (0..self.windows.len())
+            .filter(|index| {
+                let window = &self.windows[*index];
+                window.monitor == monitor && window.mapped && !window.floating && !window.fullscreen
+            })
+            .collect();
+
+        let (layout, master_ratio, master_count, order) = {
+            let state = self.current_tag_state(monitor);
+            (
+                state.layout,
+                state.master_ratio,
+                state.master_count,
+                state.order.clone(),
+            )
+        };
+        // Windows of the primary tag come first in its order, windows of other tags follow
+        let windows = &self.windows;
+        mapped_window_index.sort_by_key(|index| {
+            order
+                .iter()
+                .position(|id| *id == windows[*index].window_id)
+                .unwrap_or(order.len())
+        });
+
+        let area = self.tiling_area(monitor);
+        let window_ids: Vec<u32> = mapped_window_index
+            .iter()
+            .map(|index| self.windows[*index].window_id)
+            .collect();
+        let geometries =
+            self.layouts[layout].arrange(&window_ids, area, master_ratio, master_count);
+
+        for (index, geometry) in mapped_window_index.iter().zip(geometries) {
+            let window = &mut self.windows[*index];
+            let border = window.border_override.unwrap_or(border);
+            let (width, height) = window.size_hints.apply(
+                geometry.width.saturating_sub(2 * border),
+                geometry.height.saturating_sub(2 * border),
+            );
+            window.x = geometry.x;
+            window.y = geometry.y;
+            window.width = width;
+            window.height = height;
         }
     }
 
@@ -134,17 +429,168 @@ impl LayoutManager {
                 root.unmap_window(window.window_id);
             }
         }
+        // Floating windows always stay above the tiled ones, and fullscreen windows above all
+        for window in &self.windows {
+            if window.mapped && window.floating && !window.fullscreen {
+                root.raise_window(window.window_id);
+            }
+        }
+        for window in &self.windows {
+            if window.mapped && window.fullscreen {
+                root.raise_window(window.window_id);
+            }
+        }
         root.flush();
     }
 
+    pub fn is_managed(&self, window_id: u32) -> bool {
+        self.windows
+            .iter()
+            .any(|window| window.window_id == window_id)
+    }
+
+    pub fn is_floating(&self, window_id: u32) -> bool {
+        self.windows
+            .iter()
+            .any(|window| window.window_id == window_id && window.floating)
+    }
+
+    pub fn get_geometry(&self, window_id: u32) -> Option<Geometry> {
+        self.windows
+            .iter()
+            .find(|window| window.window_id == window_id)
+            .map(|window| Geometry::new(window.x, window.y, window.width, window.height))
+    }
+
+    pub fn get_client_list(&self) -> Vec<u32> {
+        self.windows.iter().map(|window| window.window_id).collect()
+    }
+
+    // Floating windows are stacked above tiled ones, fullscreen windows above all
+    pub fn get_client_list_stacking(&self) -> Vec<u32> {
+        let tiled = self
+            .windows
+            .iter()
+            .filter(|window| !window.floating && !window.fullscreen);
+        let floating = self
+            .windows
+            .iter()
+            .filter(|window| window.floating && !window.fullscreen);
+        let fullscreen = self.windows.iter().filter(|window| window.fullscreen);
+        tiled
+            .chain(floating)
+            .chain(fullscreen)
+            .map(|window| window.window_id)
+            .collect()
+    }
+
+    pub fn get_border(&self, window_id: u32) -> u32 {
+        self.windows
+            .iter()
+            .find(|window| window.window_id == window_id)
+            .map(|window| window.border)
+            .unwrap_or(0)
+    }
+
+    // Remember the geometry (excluding border) a new window asks for, used when it floats or
+    // leaves fullscreen before it is ever laid out
+    pub fn set_geometry(&mut self, window_id: u32, geometry: Geometry) {
+        for window in &mut self.windows {
+            if window.window_id == window_id {
+                window.x = geometry.x;
+                window.y = geometry.y;
+                window.width = geometry.width;
+                window.height = geometry.height;
+            }
+        }
+    }
+
+    // Make the window floating with the given geometry (excluding border)
+    pub fn set_floating_geometry(&mut self, window_id: u32, geometry: Geometry) {
+        for window in &mut self.windows {
+            if window.window_id == window_id {
+                let (width, height) = window.size_hints.apply(geometry.width, geometry.height);
+                window.floating = true;
+                window.x = geometry.x;
+                window.y = geometry.y;
+                window.width = width;
+                window.height = height;
+            }
+        }
+    }
+
+    // Fixed size windows can't be tiled, so they float at the center of the monitor
+    pub fn set_size_hints(&mut self, window_id: u32, size_hints: SizeHints) {
+        let monitor = match self.get_monitor(window_id) {
+            Some(monitor) => monitor,
+            None => return,
+        };
+        let area = self.tiling_area(monitor);
+        for window in &mut self.windows {
+            if window.window_id == window_id {
+                window.size_hints = size_hints;
+                if size_hints.is_fixed() && !window.floating {
+                    let (width, height) = size_hints.apply(window.width, window.height);
+                    window.floating = true;
+                    window.width = width;
+                    window.height = height;
+                    window.x = area.x + area.width.saturating_sub(width) / 2;
+                    window.y = area.y + area.height.saturating_sub(height) / 2;
+                }
+            }
+        }
+    }
+
+    pub fn set_border_override(&mut self, window_id: u32, border: u32) {
+        for window in &mut self.windows {
+            if window.window_id == window_id {
+                window.border_override = Some(border);
+            }
+        }
+    }
+
+    pub fn set_floating(&mut self, window_id: u32, floating: bool) {
+        for window in &mut self.windows {
+            if window.window_id == window_id {
+                window.floating = floating;
+                if floating {
+                    let (width, height) = window.size_hints.apply(window.width, window.height);
+                    window.width = width;
+                    window.height = height;
+                }
+            }
+        }
+    }
+
+    pub fn toggle_floating(&mut self) {
+        if let Some(window_id) = self.get_focused_window() {
+            let floating = self.is_floating(window_id);
+            info!("Set window {} floating: {}", window_id, !floating);
+            self.set_floating(window_id, !floating);
+        }
+    }
+
     pub fn manage(&mut self, window_id: u32) {
+        if self.is_managed(window_id) {
+            return;
+        }
         info!("Manage window {}", window_id);
+        let monitor = self.focused_monitor;
         let mut tags = HashSet::new();
         tags.insert(0);
+        self.add_window_to_tag(window_id, monitor, 0);
         self.windows.push(Window {
             window_id,
             focused: false,
+            floating: false,
             tags,
+            monitor,
+            size_hints: SizeHints::default(),
+            urgent: None,
+            fullscreen: false,
+            saved_geometry: None,
+            transient_for: None,
+            border_override: None,
 
             width: 0,
             height: 0,
@@ -156,10 +602,13 @@ impl LayoutManager {
         });
     }
 
+    // The monitor of the focused window becomes the focused monitor
     pub fn focus(&mut self, window_id: u32) {
         for window in &mut self.windows {
             if window.window_id == window_id {
-                window.focused = true
+                window.urgent = None;
+                window.focused = true;
+                self.focused_monitor = window.monitor;
             } else {
                 window.focused = false
             }
@@ -168,16 +617,186 @@ impl LayoutManager {
 
     pub fn unmanage(&mut self, window_id: u32) {
         info!("Unmanage window {}", window_id);
+        self.remove_window_from_tags(window_id);
         self.windows
             .retain(|item: &Window| item.window_id != window_id);
     }
 
-    pub fn move_focused_window_to(&mut self, tag: u32) {
+    pub fn is_visible(&self, window_id: u32) -> bool {
+        self.windows
+            .iter()
+            .any(|window| window.window_id == window_id && window.mapped)
+    }
+
+    pub fn is_fullscreen(&self, window_id: u32) -> bool {
+        self.windows
+            .iter()
+            .any(|window| window.window_id == window_id && window.fullscreen)
+    }
+
+    pub fn set_fullscreen(&mut self, window_id: u32, fullscreen: bool) {
         for window in &mut self.windows {
-            if window.focused {
-                window.tags.clear();
-                window.tags.insert(tag);
+            if window.window_id == window_id && window.fullscreen != fullscreen {
+                info!("Set window {} fullscreen: {}", window_id, fullscreen);
+                window.fullscreen = fullscreen;
+                if fullscreen {
+                    window.saved_geometry = Some(Geometry::new(
+                        window.x,
+                        window.y,
+                        window.width,
+                        window.height,
+                    ));
+                } else if let Some(geometry) = window.saved_geometry.take() {
+                    window.x = geometry.x;
+                    window.y = geometry.y;
+                    window.width = geometry.width;
+                    window.height = geometry.height;
+                }
+            }
+        }
+    }
+
+    pub fn is_urgent(&self, window_id: u32) -> bool {
+        self.windows
+            .iter()
+            .any(|window| window.window_id == window_id && window.urgent.is_some())
+    }
+
+    pub fn set_urgent(&mut self, window_id: u32, urgent: bool) {
+        self.urgent_sequence += 1;
+        for window in &mut self.windows {
+            if window.window_id == window_id {
+                if !urgent {
+                    window.urgent = None;
+                } else if window.urgent.is_none() {
+                    window.urgent = Some(self.urgent_sequence);
+                }
+            }
+        }
+    }
+
+    pub fn get_urgent_tags(&self, monitor: usize) -> HashSet<u32> {
+        let mut tags = HashSet::new();
+        for window in &self.windows {
+            if window.urgent.is_some() && window.monitor == monitor {
+                tags.extend(window.tags.iter());
+            }
+        }
+        tags
+    }
+
+    // The window which became urgent most recently
+    pub fn get_latest_urgent(&self) -> Option<u32> {
+        self.windows
+            .iter()
+            .filter(|window| window.urgent.is_some())
+            .max_by_key(|window| window.urgent)
+            .map(|window| window.window_id)
+    }
+
+    pub fn get_focused_window(&self) -> Option<u32> {
+        self.windows
+            .iter()
+            .find(|window| window.focused)
+            .map(|window| window.window_id)
+    }
+
+    // The focused window if it is shown on the monitor, or the first shown window of it
+    pub fn get_shown_window(&self, monitor: usize) -> Option<u32> {
+        let shown = |window: &&Window| window.monitor == monitor && window.mapped;
+        self.windows
+            .iter()
+            .filter(shown)
+            .find(|window| window.focused)
+            .or_else(|| self.windows.iter().find(shown))
+            .map(|window| window.window_id)
+    }
+
+    pub fn get_tags(&self, window_id: u32) -> Option<HashSet<u32>> {
+        self.windows
+            .iter()
+            .find(|window| window.window_id == window_id)
+            .map(|window| window.tags.clone())
+    }
+
+    // Transient windows are moved together with their parent
+    pub fn set_tags(&mut self, window_id: u32, tags: HashSet<u32>) {
+        if !self.is_managed(window_id) || tags.is_empty() {
+            return;
+        }
+        let monitor = self.get_monitor(window_id).unwrap();
+        self.remove_window_from_tags(window_id);
+        for tag in &tags {
+            self.add_window_to_tag(window_id, monitor, *tag);
+        }
+        for window in &mut self.windows {
+            if window.window_id == window_id {
+                window.tags = tags.clone();
+            }
+        }
+
+        for transient in self.get_transients(window_id) {
+            if self.get_tags(transient) != Some(tags.clone()) {
+                self.set_tags(transient, tags.clone());
+            }
+        }
+    }
+
+    pub fn get_transients(&self, window_id: u32) -> Vec<u32> {
+        self.windows
+            .iter()
+            .filter(|window| window.transient_for == Some(window_id))
+            .map(|window| window.window_id)
+            .collect()
+    }
+
+    // The transient window inherits the monitor and tags of its parent
+    pub fn set_transient_for(&mut self, window_id: u32, parent: u32) {
+        let monitor = self.get_monitor(parent);
+        for window in &mut self.windows {
+            if window.window_id == window_id {
+                window.transient_for = Some(parent);
+                window.monitor = monitor.unwrap_or(window.monitor);
             }
         }
+        if let Some(tags) = self.get_tags(parent) {
+            self.set_tags(window_id, tags);
+        }
+    }
+
+    pub fn get_monitor(&self, window_id: u32) -> Option<usize> {
+        self.windows
+            .iter()
+            .find(|window| window.window_id == window_id)
+            .map(|window| window.monitor)
+    }
+
+    // Move the window to the shown tags of another monitor, floating windows keep their
+    // position relative to the monitor. Transient windows follow their parent.
+    pub fn set_monitor(&mut self, window_id: u32, monitor: usize) {
+        let old_monitor = match self.get_monitor(window_id) {
+            Some(old_monitor) => old_monitor,
+            None => return,
+        };
+        if monitor >= self.monitors.len() || monitor == old_monitor {
+            return;
+        }
+        info!("Move window {} to monitor {}", window_id, monitor);
+
+        let from = self.monitors[old_monitor].geometry;
+        let to = self.monitors[monitor].geometry;
+        for window in &mut self.windows {
+            if window.window_id == window_id {
+                window.monitor = monitor;
+                window.x = (window.x as i32 - from.x as i32 + to.x as i32).max(0) as u32;
+                window.y = (window.y as i32 - from.y as i32 + to.y as i32).max(0) as u32;
+            }
+        }
+        let tags = self.monitors[monitor].current_tag.borrow().clone();
+        self.set_tags(window_id, tags);
+
+        for transient in self.get_transients(window_id) {
+            self.set_monitor(transient, monitor);
+        }
     }
 }
@@ -0,0 +1,34 @@
+use super::Geometry;
+use crate::utils::strut::Strut;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+// An output of RandR, tiled on its own with its own tags
+pub struct Monitor {
+    pub geometry: Geometry,
+    pub current_tag: Arc<RefCell<HashSet<u32>>>,
+    // Space reserved by docks and the bar on this monitor
+    pub strut: Strut,
+    pub bar: Strut,
+}
+
+impl Monitor {
+    pub fn new(geometry: Geometry) -> Monitor {
+        let mut current_tag = HashSet::new();
+        current_tag.insert(0);
+        Monitor {
+            geometry,
+            current_tag: Arc::new(RefCell::new(current_tag)),
+            strut: Strut::default(),
+            bar: Strut::default(),
+        }
+    }
+
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        x >= self.geometry.x
+            && x < self.geometry.x + self.geometry.width
+            && y >= self.geometry.y
+            && y < self.geometry.y + self.geometry.height
+    }
+}
@@ -13,6 +13,7 @@ mod bar;
 mod keyboard;
 mod layout_manager;
 mod oscillator;
+mod rule;
 mod setting;
 mod utils;
 
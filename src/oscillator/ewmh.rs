@@ -0,0 +1,476 @@
+use crate::layout_manager::Geometry;
+use crate::oscillator::Oscillator;
+use crate::oscillator::TAG_COUNT;
+use crate::setting::ActivationPolicy;
+use crate::utils::strut::PartialStrut;
+use crate::utils::strut::Strut;
+
+// How a window is treated according to _NET_WM_WINDOW_TYPE
+#[derive(PartialEq)]
+pub enum WindowKind {
+    Normal,
+    Dock,
+    Floating,
+    Unmanaged,
+}
+
+impl Oscillator {
+    // Create the child window for _NET_SUPPORTING_WM_CHECK and advertise supported hints
+    pub fn setup_ewmh(&self) {
+        let check_window = self.check_window;
+        xcb::create_window(
+            &self.connection,
+            xcb::COPY_FROM_PARENT as u8,
+            check_window,
+            self.window_id,
+            -1,
+            -1,
+            1,
+            1,
+            0,
+            xcb::WINDOW_CLASS_INPUT_ONLY as u16,
+            xcb::COPY_FROM_PARENT,
+            &[],
+        );
+
+        for window in &[self.window_id, check_window] {
+            xcb::change_property(
+                &self.connection,
+                xcb::PROP_MODE_REPLACE as u8,
+                *window,
+                self.atoms.net_supporting_wm_check,
+                xcb::ATOM_WINDOW,
+                32,
+                &[check_window],
+            );
+        }
+        xcb::change_property(
+            &self.connection,
+            xcb::PROP_MODE_REPLACE as u8,
+            check_window,
+            self.atoms.net_wm_name,
+            self.atoms.utf8_string,
+            8,
+            "Oscillator".as_bytes(),
+        );
+
+        xcb::change_property(
+            &self.connection,
+            xcb::PROP_MODE_REPLACE as u8,
+            self.window_id,
+            self.atoms.net_supported,
+            xcb::ATOM_ATOM,
+            32,
+            &self.atoms.supported(),
+        );
+
+        // Every tag is published as a desktop named by its number
+        xcb::change_property(
+            &self.connection,
+            xcb::PROP_MODE_REPLACE as u8,
+            self.window_id,
+            self.atoms.net_number_of_desktops,
+            xcb::ATOM_CARDINAL,
+            32,
+            &[TAG_COUNT],
+        );
+        let mut names = String::new();
+        for tag in 0..TAG_COUNT {
+            names.push_str(&format!("{}\0", tag));
+        }
+        xcb::change_property(
+            &self.connection,
+            xcb::PROP_MODE_REPLACE as u8,
+            self.window_id,
+            self.atoms.net_desktop_names,
+            self.atoms.utf8_string,
+            8,
+            names.as_bytes(),
+        );
+
+        self.update_current_desktop();
+        self.update_workarea();
+        self.update_client_list();
+    }
+
+    // When several tags are selected, the lowest one of the focused monitor is published as
+    // current desktop
+    pub fn update_current_desktop(&self) {
+        let current_desktop = {
+            let layout_manager = self.layout_manager.borrow();
+            layout_manager.primary_tag(layout_manager.get_focused_monitor())
+        };
+        xcb::change_property(
+            &self.connection,
+            xcb::PROP_MODE_REPLACE as u8,
+            self.window_id,
+            self.atoms.net_current_desktop,
+            xcb::ATOM_CARDINAL,
+            32,
+            &[current_desktop],
+        );
+    }
+
+    pub fn handle_client_message(&self, event: &xcb::ClientMessageEvent) {
+        let data = event.data().data32();
+        let message_type = event.type_();
+
+        let window = event.window();
+        let managed = self.layout_manager.borrow().is_managed(window);
+
+        if message_type == self.atoms.net_current_desktop && data[0] < TAG_COUNT {
+            info!("Select Tag: \"{}\" by client message", data[0]);
+            self.select_tag(data[0]);
+        } else if message_type == self.atoms.net_wm_desktop && data[0] < TAG_COUNT && managed {
+            self.move_window_to_tag(window, data[0]);
+        } else if message_type == self.atoms.net_active_window && managed {
+            self.activate_window(window);
+        } else if message_type == self.atoms.net_wm_state && managed {
+            self.handle_wm_state_message(window, data);
+        }
+    }
+
+    // data[0] is the action, data[1] and data[2] are the properties to change
+    fn handle_wm_state_message(&self, window: u32, data: &[u32]) {
+        const NET_WM_STATE_REMOVE: u32 = 0;
+        const NET_WM_STATE_ADD: u32 = 1;
+        const NET_WM_STATE_TOGGLE: u32 = 2;
+
+        if data[1] == self.atoms.net_wm_state_fullscreen
+            || data[2] == self.atoms.net_wm_state_fullscreen
+        {
+            let fullscreen = self.layout_manager.borrow().is_fullscreen(window);
+            match data[0] {
+                NET_WM_STATE_REMOVE => self.set_fullscreen(window, false),
+                NET_WM_STATE_ADD => self.set_fullscreen(window, true),
+                NET_WM_STATE_TOGGLE => self.set_fullscreen(window, !fullscreen),
+                _ => {}
+            }
+        }
+
+        if data[1] == self.atoms.net_wm_state_demands_attention
+            || data[2] == self.atoms.net_wm_state_demands_attention
+        {
+            let demands_attention = self.demands_attention.borrow().contains(&window);
+            let demands_attention = match data[0] {
+                NET_WM_STATE_REMOVE => false,
+                NET_WM_STATE_ADD => true,
+                NET_WM_STATE_TOGGLE => !demands_attention,
+                _ => demands_attention,
+            };
+            if demands_attention {
+                self.demands_attention.borrow_mut().insert(window);
+            } else {
+                self.demands_attention.borrow_mut().remove(&window);
+            }
+            self.update_urgency(window);
+        }
+    }
+
+    pub fn get_net_wm_state(&self, window: u32) -> Vec<xcb::Atom> {
+        match xcb::get_property(
+            &self.connection,
+            false,
+            window,
+            self.atoms.net_wm_state,
+            xcb::ATOM_ATOM,
+            0,
+            32,
+        )
+        .get_reply()
+        {
+            Ok(reply) => reply.value::<xcb::Atom>().to_vec(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    // Fullscreen windows cover their whole monitor without border, above the bar
+    pub fn set_fullscreen(&self, window: u32, fullscreen: bool) {
+        self.layout_manager
+            .borrow_mut()
+            .set_fullscreen(window, fullscreen);
+        self.update_wm_state(window);
+
+        self.layout_manager.borrow_mut().recalc();
+        self.layout_manager.borrow().sync(self);
+        self.update_client_list();
+    }
+
+    // Only the states oscillator owns are changed, the others set by the client are kept
+    pub fn update_wm_state(&self, window: u32) {
+        let mut states: Vec<xcb::Atom> = self
+            .get_net_wm_state(window)
+            .into_iter()
+            .filter(|state| {
+                *state != self.atoms.net_wm_state_fullscreen
+                    && *state != self.atoms.net_wm_state_demands_attention
+            })
+            .collect();
+        if self.layout_manager.borrow().is_fullscreen(window) {
+            states.push(self.atoms.net_wm_state_fullscreen);
+        }
+        if self.layout_manager.borrow().is_urgent(window) {
+            states.push(self.atoms.net_wm_state_demands_attention);
+        }
+        xcb::change_property(
+            &self.connection,
+            xcb::PROP_MODE_REPLACE as u8,
+            window,
+            self.atoms.net_wm_state,
+            xcb::ATOM_ATOM,
+            32,
+            &states,
+        );
+    }
+
+    pub fn update_active_window(&self, window: u32) {
+        let active_window = if window == self.window_id {
+            xcb::NONE
+        } else {
+            window
+        };
+        xcb::change_property(
+            &self.connection,
+            xcb::PROP_MODE_REPLACE as u8,
+            self.window_id,
+            self.atoms.net_active_window,
+            xcb::ATOM_WINDOW,
+            32,
+            &[active_window],
+        );
+    }
+
+    // Handle a _NET_ACTIVE_WINDOW request according to the activation policy
+    pub fn activate_window(&self, window: u32) {
+        match self.settings.get_activation_policy() {
+            ActivationPolicy::Focus => {
+                info!("Activate window {}", window);
+                self.show_and_focus(window);
+            }
+            ActivationPolicy::Urgent => {
+                info!("Mark window {} urgent", window);
+                self.demands_attention.borrow_mut().insert(window);
+                self.update_urgency(window);
+            }
+            ActivationPolicy::Ignore => {}
+        }
+    }
+
+    // _NET_CLIENT_LIST is in mapping order, _NET_CLIENT_LIST_STACKING is from bottom to top
+    pub fn update_client_list(&self) {
+        let layout_manager = self.layout_manager.borrow();
+        xcb::change_property(
+            &self.connection,
+            xcb::PROP_MODE_REPLACE as u8,
+            self.window_id,
+            self.atoms.net_client_list,
+            xcb::ATOM_WINDOW,
+            32,
+            &layout_manager.get_client_list(),
+        );
+        xcb::change_property(
+            &self.connection,
+            xcb::PROP_MODE_REPLACE as u8,
+            self.window_id,
+            self.atoms.net_client_list_stacking,
+            xcb::ATOM_WINDOW,
+            32,
+            &layout_manager.get_client_list_stacking(),
+        );
+    }
+
+    pub fn get_window_kind(&self, window: u32) -> WindowKind {
+        let reply = match xcb::get_property(
+            &self.connection,
+            false,
+            window,
+            self.atoms.net_wm_window_type,
+            xcb::ATOM_ATOM,
+            0,
+            32,
+        )
+        .get_reply()
+        {
+            Ok(reply) => reply,
+            Err(_) => return WindowKind::Normal,
+        };
+
+        // The first type known by oscillator decides
+        for window_type in reply.value::<xcb::Atom>() {
+            let window_type = *window_type;
+            if window_type == self.atoms.net_wm_window_type_dock {
+                return WindowKind::Dock;
+            }
+            if window_type == self.atoms.net_wm_window_type_dialog
+                || window_type == self.atoms.net_wm_window_type_utility
+                || window_type == self.atoms.net_wm_window_type_splash
+            {
+                return WindowKind::Floating;
+            }
+            if window_type == self.atoms.net_wm_window_type_desktop
+                || window_type == self.atoms.net_wm_window_type_notification
+                || window_type == self.atoms.net_wm_window_type_tooltip
+                || window_type == self.atoms.net_wm_window_type_popup_menu
+                || window_type == self.atoms.net_wm_window_type_dropdown_menu
+                || window_type == self.atoms.net_wm_window_type_combo
+                || window_type == self.atoms.net_wm_window_type_dnd
+            {
+                return WindowKind::Unmanaged;
+            }
+        }
+        WindowKind::Normal
+    }
+
+    // Docks are mapped but not managed, the space they take is reserved
+    pub fn manage_dock(&self, window: u32) {
+        info!("Dock window {}", window);
+        self.docks.borrow_mut().insert(window);
+        xcb::change_window_attributes(
+            &self.connection,
+            window,
+            &[(
+                xcb::CW_EVENT_MASK,
+                xcb::EVENT_MASK_STRUCTURE_NOTIFY | xcb::EVENT_MASK_PROPERTY_CHANGE,
+            )],
+        );
+        xcb::map_window(&self.connection, window);
+        self.update_strut();
+    }
+
+    // Read _NET_WM_STRUT_PARTIAL or _NET_WM_STRUT of the dock
+    fn get_dock_strut(&self, dock: u32) -> Option<PartialStrut> {
+        for property in &[self.atoms.net_wm_strut_partial, self.atoms.net_wm_strut] {
+            let reply = xcb::get_property(
+                &self.connection,
+                false,
+                dock,
+                *property,
+                xcb::ATOM_CARDINAL,
+                0,
+                12,
+            )
+            .get_reply();
+            if let Ok(reply) = reply {
+                if let Some(strut) = PartialStrut::from_raw(reply.value::<u32>()) {
+                    return Some(strut);
+                }
+            }
+        }
+        None
+    }
+
+    // Reserve the space of docks on every monitor, and publish the rest as _NET_WORKAREA
+    pub fn update_strut(&self) {
+        let screen_width = self.width.get() as u32;
+        let screen_height = self.height.get() as u32;
+        let dock_struts: Vec<PartialStrut> = self
+            .docks
+            .borrow()
+            .iter()
+            .filter_map(|dock| self.get_dock_strut(*dock))
+            .collect();
+
+        let monitor_count = self.layout_manager.borrow().monitor_count();
+        for index in 0..monitor_count {
+            let monitor = self.layout_manager.borrow().get_monitor_geometry(index);
+            let mut strut = Strut::default();
+            for dock_strut in &dock_struts {
+                strut = strut.merge(&dock_strut.on_area(monitor, screen_width, screen_height));
+            }
+
+            info!(
+                "Reserve on monitor {} LEFT: {} RIGHT: {} TOP: {} BOTTOM: {}",
+                index, strut.left, strut.right, strut.top, strut.bottom
+            );
+            self.layout_manager.borrow_mut().set_strut(index, strut);
+        }
+        self.update_workarea();
+    }
+
+    // Publish the reservation of a window created by oscillator
+    pub fn set_window_strut(&self, window: u32, strut: &PartialStrut) {
+        let raw = strut.to_raw();
+        xcb::change_property(
+            &self.connection,
+            xcb::PROP_MODE_REPLACE as u8,
+            window,
+            self.atoms.net_wm_strut_partial,
+            xcb::ATOM_CARDINAL,
+            32,
+            &raw,
+        );
+        xcb::change_property(
+            &self.connection,
+            xcb::PROP_MODE_REPLACE as u8,
+            window,
+            self.atoms.net_wm_strut,
+            xcb::ATOM_CARDINAL,
+            32,
+            &raw[..4],
+        );
+    }
+
+    // _NET_WORKAREA has no notion of monitors, the whole screen without the space docks and
+    // bars reserve on its edges is published
+    pub fn update_workarea(&self) {
+        let screen_width = self.width.get() as u32;
+        let screen_height = self.height.get() as u32;
+        let screen = Geometry::new(0, 0, screen_width, screen_height);
+
+        let mut strut = Strut::default();
+        for dock in self.docks.borrow().iter() {
+            if let Some(dock_strut) = self.get_dock_strut(*dock) {
+                strut = strut.merge(&dock_strut.on_area(screen, screen_width, screen_height));
+            }
+        }
+        for bar in self.bars.borrow().iter() {
+            let bar_strut = bar.partial_strut(screen_height);
+            strut = strut.merge(&bar_strut.on_area(screen, screen_width, screen_height));
+        }
+
+        let area = [
+            strut.left,
+            strut.top,
+            screen_width.saturating_sub(strut.left + strut.right),
+            screen_height.saturating_sub(strut.top + strut.bottom),
+        ];
+        let mut workarea = Vec::new();
+        for _ in 0..TAG_COUNT {
+            workarea.extend_from_slice(&area);
+        }
+        xcb::change_property(
+            &self.connection,
+            xcb::PROP_MODE_REPLACE as u8,
+            self.window_id,
+            self.atoms.net_workarea,
+            xcb::ATOM_CARDINAL,
+            32,
+            &workarea,
+        );
+    }
+
+    // Dialogs, utility windows and splash screens float at the center of their parent
+    pub fn float_over_parent(&self, window: u32) {
+        let (width, height) = match xcb::get_geometry(&self.connection, window).get_reply() {
+            Ok(geometry) => (geometry.width() as u32, geometry.height() as u32),
+            Err(_) => return,
+        };
+
+        let parent_geometry: Option<Geometry> = self
+            .get_transient_for(window)
+            .and_then(|parent| self.layout_manager.borrow().get_geometry(parent));
+        let area = match parent_geometry {
+            Some(geometry) => geometry,
+            None => {
+                let layout_manager = self.layout_manager.borrow();
+                let monitor = layout_manager
+                    .get_monitor(window)
+                    .unwrap_or_else(|| layout_manager.get_focused_monitor());
+                layout_manager.tiling_area(monitor)
+            }
+        };
+        self.layout_manager
+            .borrow_mut()
+            .center_floating(window, area, width, height);
+    }
+}
@@ -1,26 +1,79 @@
+mod ewmh;
+mod monitor;
+
+use self::ewmh::WindowKind;
+use crate::bar::spawn_status_command;
 use crate::bar::Bar;
+use crate::bar::BarRegion;
+use crate::keyboard::keybind::keysym_by_name;
+use crate::keyboard::keybind::parse_key_string;
+use crate::keyboard::keybind::parse_modifier;
 use crate::keyboard::keymod::MOD_MAP;
 use crate::keyboard::keysymdef::KEYSYM_MAP;
+use crate::layout_manager::Geometry;
 use crate::layout_manager::LayoutManager;
+use crate::rule::WindowProperties;
 use crate::setting::Key;
 use crate::setting::Settings;
+use crate::utils::atom::Atoms;
 use crate::utils::color::Color;
 use crate::utils::font::TextExtends;
+use crate::utils::size_hints::SizeHints;
 use image::GenericImageView;
+use std::cell::Cell;
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::collections::HashSet;
+use std::sync::mpsc::Receiver;
 use std::sync::Arc;
 
+pub const TAG_COUNT: u32 = 10;
+
+// Values of WM_STATE in ICCCM
+const WM_STATE_WITHDRAWN: u32 = 0;
+const WM_STATE_NORMAL: u32 = 1;
+const WM_STATE_ICONIC: u32 = 3;
+
+// XUrgencyHint in the flags of WM_HINTS
+const URGENCY_HINT: u32 = 1 << 8;
+
+// A window being moved or resized with the mouse
+struct Drag {
+    window: u32,
+    button: u8,
+    pointer_x: i32,
+    pointer_y: i32,
+    geometry: Geometry,
+}
+
 pub struct Oscillator {
     pub connection: Arc<xcb::Connection>,
-    pub current_tag: Arc<RefCell<HashSet<u32>>>,
+    pub atoms: Atoms,
     screen_num: i32,
     window_id: u32,
-    height: i32,
-    width: i32,
+    check_window: u32,
+    // Size of the whole screen, changed by RandR
+    height: Cell<i32>,
+    width: Cell<i32>,
+    // First event code of RandR, None if the extension is missing
+    randr_event_base: Option<u8>,
     settings: Arc<Settings>,
     layout_manager: RefCell<LayoutManager>,
-    bar: RefCell<Bar>,
+    bars: RefCell<Vec<Bar>>,
+    lock_mask: Cell<u16>,
+    drag: RefCell<Option<Drag>>,
+    // Cursors shown while moving and resizing a window
+    move_cursor: u32,
+    resize_cursor: u32,
+    // Windows mapped by oscillator, and the number of UnmapNotify caused by oscillator itself
+    shown_windows: RefCell<HashSet<u32>>,
+    ignore_unmap: RefCell<HashMap<u32, u32>>,
+    docks: RefCell<HashSet<u32>>,
+    // Windows asking for attention through _NET_WM_STATE, apart from XUrgencyHint
+    demands_attention: RefCell<HashSet<u32>>,
+    // Text at the right of the bars, from the status command or root WM_NAME
+    status: RefCell<String>,
+    status_receiver: Option<Receiver<String>>,
 }
 
 impl Oscillator {
@@ -33,31 +86,60 @@ impl Oscillator {
         let width = screen.width_in_pixels() as i32;
         let height = screen.height_in_pixels() as i32;
 
+        let atoms = Atoms::new(&connection);
         let settings = Arc::new(settings);
+        let monitors =
+            Oscillator::query_monitors(&connection, root_id, width as u32, height as u32);
+        let layout_manager = LayoutManager::new(settings.clone(), &monitors);
+        let bars = monitors
+            .iter()
+            .enumerate()
+            .map(|(index, monitor)| {
+                Bar::new(
+                    settings.clone(),
+                    index,
+                    *monitor,
+                    layout_manager.get_current_tag(index),
+                )
+            })
+            .collect();
+        let randr_event_base = connection
+            .get_extension_data(xcb::randr::id())
+            .filter(|randr| randr.present())
+            .map(|randr| randr.first_event());
+        let status_receiver = settings
+            .get_bar()
+            .status_command
+            .as_ref()
+            .and_then(|command| spawn_status_command(command, root_id));
         let connection = Arc::new(connection);
-        let current_tag = Arc::new(RefCell::new(HashSet::new()));
-        current_tag.borrow_mut().insert(0);
         let _self = Oscillator {
             connection: connection.clone(),
+            atoms,
             screen_num,
             window_id: root_id,
-            width,
-            height,
+            check_window: connection.generate_id(),
+            width: Cell::new(width),
+            height: Cell::new(height),
+            randr_event_base,
             settings: settings.clone(),
-            current_tag: current_tag.clone(),
-            layout_manager: std::cell::RefCell::new(LayoutManager::new(
-                settings.clone(),
-                width as u32,
-                height as u32,
-                current_tag.clone(),
-            )),
-            bar: std::cell::RefCell::new(Bar::new(
-                settings.clone(),
-                width as u32,
-                current_tag.clone(),
-            )),
+            layout_manager: std::cell::RefCell::new(layout_manager),
+            bars: std::cell::RefCell::new(bars),
+            lock_mask: Cell::new(xcb::MOD_MASK_LOCK as u16),
+            drag: RefCell::new(None),
+            move_cursor: connection.generate_id(),
+            resize_cursor: connection.generate_id(),
+            shown_windows: RefCell::new(HashSet::new()),
+            ignore_unmap: RefCell::new(HashMap::new()),
+            docks: RefCell::new(HashSet::new()),
+            demands_attention: RefCell::new(HashSet::new()),
+            status: RefCell::new(String::new()),
+            status_receiver,
         };
-        _self.bar.borrow_mut().prepare(&_self);
+        for bar in _self.bars.borrow_mut().iter_mut() {
+            bar.prepare(&_self);
+        }
+        _self.update_bar_struts();
 
         const EVENT_MASK: u32 = xcb::EVENT_MASK_KEY_PRESS
             | xcb::EVENT_MASK_BUTTON_PRESS
@@ -66,7 +148,8 @@ impl Oscillator {
             | xcb::EVENT_MASK_LEAVE_WINDOW
             | xcb::EVENT_MASK_STRUCTURE_NOTIFY
             | xcb::EVENT_MASK_SUBSTRUCTURE_NOTIFY
-            | xcb::EVENT_MASK_SUBSTRUCTURE_REDIRECT;
+            | xcb::EVENT_MASK_SUBSTRUCTURE_REDIRECT
+            | xcb::EVENT_MASK_PROPERTY_CHANGE;
         xcb::map_window(&_self.connection, _self.window_id);
 
         let title = "Oscillator";
@@ -79,6 +162,7 @@ impl Oscillator {
             8,
             title.as_bytes(),
         );
+        _self.setup_ewmh();
 
         let cursor_font = _self.connection.generate_id();
         xcb::open_font(&_self.connection, cursor_font, "cursor");
@@ -97,20 +181,47 @@ impl Oscillator {
             0,
             0,
         );
+        // fleur for moving, sizing for resizing
+        for (cursor, glyph) in &[(_self.move_cursor, 52), (_self.resize_cursor, 120)] {
+            xcb::create_glyph_cursor(
+                &_self.connection,
+                *cursor,
+                cursor_font,
+                cursor_font,
+                *glyph,
+                glyph + 1,
+                0,
+                0,
+                0,
+                0xffff,
+                0xffff,
+                0xffff,
+            );
+        }
         xcb::change_window_attributes(
             &_self.connection,
             _self.window_id,
             &[(xcb::CW_EVENT_MASK, EVENT_MASK), (xcb::CW_CURSOR, cursor)],
         );
-        info!(
-            "Setup root window. Width: {}, Height: {}",
-            _self.width, _self.height
-        );
+        info!("Setup root window. Width: {}, Height: {}", width, height);
+        if _self.randr_event_base.is_some() {
+            xcb::randr::select_input(
+                &_self.connection,
+                _self.window_id,
+                (xcb::randr::NOTIFY_MASK_SCREEN_CHANGE | xcb::randr::NOTIFY_MASK_CRTC_CHANGE)
+                    as u16,
+            );
+        }
 
         _self.set_background(settings.get_background());
+        _self.grab_keys();
+        _self.grab_buttons();
+
+        _self.adopt_windows();
 
         _self.focus(_self.window_id);
-        _self.bar.borrow().draw(&_self);
+        // Lines printed before the root window listens to the wake up message
+        _self.receive_status();
 
         _self.flush();
 
@@ -134,78 +245,16 @@ impl Oscillator {
 
                             let key_symbols = xcb_util::keysyms::KeySymbols::new(&self.connection);
                             let keysym = key_symbols.press_lookup_keysym(key_press_event, 0); //TODO: what is col?
-                            let keymod = key_press_event.state();
-
-                            let mut key_string: String = format!("{}", KEYSYM_MAP[&keysym]);
-                            for i in 0..8 {
-                                if keymod & 1 << (7 - i) > 0 {
-                                    key_string =
-                                        format!("{}-{}", MOD_MAP[&(1 << (7 - i))], key_string);
-                                }
-                            }
+                            let key_string = self.binding_string(
+                                key_press_event.state(),
+                                &format!("{}", KEYSYM_MAP[&keysym]),
+                            );
                             info!("Trigger {}", key_string);
 
-                            match &self.settings.get_keys().get(&key_string.to_lowercase()) {
-                                Some(Key::Spawn { command }) => {
-                                    info!("Spawn: \"{}\"", command.join(" "));
-                                    let mut proc = std::process::Command::new(&command[0]);
-                                    for arg in 1..command.len() {
-                                        proc.arg(&command[arg]);
-                                    }
-                                    match proc.spawn() {
-                                        Err(_) => {
-                                            info!("Spawn Failed"); //TODO: More detailed spawn failed information
-                                        }
-                                        _ => info!("Spawn \"{}\" successful", command.join(" ")),
-                                    }
-                                }
-                                Some(Key::Quit) => {
-                                    info!("Quit focus window");
-
-                                    let window = xcb::get_input_focus(&self.connection)
-                                        .get_reply()
-                                        .unwrap()
-                                        .focus();
-                                    xcb::kill_client(&self.connection, window);
-                                    self.flush();
-                                }
-                                Some(Key::SelTag { tag }) => {
-                                    info!("Select Tag: \"{}\"", tag);
-
-                                    self.current_tag.borrow_mut().clear();
-                                    self.current_tag.borrow_mut().insert(*tag);
-
-                                    self.bar.borrow().draw(&self);
-                                    self.layout_manager.borrow_mut().recalc();
-                                    self.layout_manager.borrow().sync(&self);
-
-                                    self.flush();
-                                }
-                                Some(Key::TagTarget { tag }) => {
-                                    self.layout_manager
-                                        .borrow_mut()
-                                        .move_focused_window_to(*tag);
-
-                                    self.layout_manager.borrow_mut().recalc();
-                                    self.layout_manager.borrow().sync(&self);
-
-                                    self.flush();
-                                }
-                                Some(Key::SelAllTag) => {
-                                    info!("Select All Tags");
-
-                                    self.current_tag.borrow_mut().clear();
-                                    for i in 0..10 {
-                                        self.current_tag.borrow_mut().insert(i);
-                                    }
-
-                                    self.bar.borrow().draw(&self);
-                                    self.layout_manager.borrow_mut().recalc();
-                                    self.layout_manager.borrow().sync(&self);
-
-                                    self.flush();
-                                }
-                                None => {}
+                            if let Some(key) =
+                                self.settings.get_keys().get(&key_string.to_lowercase())
+                            {
+                                self.handle_key(key);
                             }
                             trace!(
                                 "Event KEY_PRESS triggered on WINDOW: {}",
@@ -215,12 +264,72 @@ impl Oscillator {
                         xcb::BUTTON_PRESS => {
                             let button_press_event: &xcb::ButtonPressEvent =
                                 unsafe { xcb::cast_event(&event) };
+
+                            let window = button_press_event.child();
+                            let button = button_press_event.detail();
+                            // Only the grabbed modifier with button 1 or 3 starts a drag
+                            let dragging =
+                                parse_modifier(self.settings.get_mouse_modifier()).is_some_and(
+                                    |modifier| button_press_event.state() & modifier == modifier,
+                                ) && (button == xcb::BUTTON_INDEX_1 as u8
+                                    || button == xcb::BUTTON_INDEX_3 as u8);
+                            if self.layout_manager.borrow().is_managed(window) {
+                                if dragging {
+                                    self.start_drag(
+                                        window,
+                                        button,
+                                        button_press_event.root_x() as i32,
+                                        button_press_event.root_y() as i32,
+                                    );
+                                }
+                            } else {
+                                // Clicks outside of the bars are ignored
+                                self.handle_bar_click(button_press_event);
+                                self.flush();
+                            }
                             trace!(
                                 "Event BUTTON_PRESS triggered on WINDOW: {}",
                                 button_press_event.event()
                             );
                         }
+                        xcb::BUTTON_RELEASE => {
+                            let drag = self.drag.borrow_mut().take();
+                            if let Some(drag) = drag {
+                                xcb::ungrab_pointer(&self.connection, xcb::CURRENT_TIME);
+                                // The window belongs to the monitor its center is dropped on
+                                let geometry =
+                                    self.layout_manager.borrow().get_geometry(drag.window);
+                                if let Some(geometry) = geometry {
+                                    let monitor = self.layout_manager.borrow().monitor_at(
+                                        geometry.x + geometry.width / 2,
+                                        geometry.y + geometry.height / 2,
+                                    );
+                                    if self.layout_manager.borrow().get_monitor(drag.window)
+                                        != Some(monitor)
+                                    {
+                                        self.layout_manager.borrow_mut().focus_monitor(monitor);
+                                        self.move_window_to_monitor(drag.window, monitor);
+                                        self.focus(drag.window);
+                                    }
+                                }
+                                self.flush();
+                            }
+                            trace!("Event BUTTON_RELEASE triggered");
+                        }
                         xcb::MOTION_NOTIFY => {
+                            let motion_notify_event: &xcb::MotionNotifyEvent =
+                                unsafe { xcb::cast_event(&event) };
+
+                            let pointer_x = motion_notify_event.root_x() as i32;
+                            let pointer_y = motion_notify_event.root_y() as i32;
+                            if self.drag.borrow().is_some() {
+                                self.update_drag(pointer_x, pointer_y);
+                            } else if motion_notify_event.event() == self.window_id
+                                && motion_notify_event.child() == xcb::NONE
+                            {
+                                self.update_monitor_focus(pointer_x, pointer_y);
+                                self.flush();
+                            }
                             trace!("Event MOTION_NOTIFY triggered");
                         }
                         xcb::ENTER_NOTIFY => {
@@ -241,6 +350,15 @@ impl Oscillator {
                             trace!("Event LEAVE_NOTIFY triggered");
                         }
                         xcb::CLIENT_MESSAGE => {
+                            let client_message_event: &xcb::ClientMessageEvent =
+                                unsafe { xcb::cast_event(&event) };
+
+                            if client_message_event.type_() == self.atoms.oscillator_status {
+                                self.receive_status();
+                            } else {
+                                self.handle_client_message(client_message_event);
+                            }
+                            self.flush();
                             trace!("Event CLIENT_MESSAGE triggered");
                         }
                         xcb::MAP_REQUEST => {
@@ -248,25 +366,65 @@ impl Oscillator {
                                 unsafe { xcb::cast_event(&event) };
 
                             let window = map_request_event.window();
-                            self.layout_manager.borrow_mut().manage(window);
-                            self.listen_window_event(window);
+                            let focus = self.handle_new_window(window);
                             self.layout_manager.borrow_mut().recalc();
                             self.layout_manager.borrow().sync(self);
+                            if focus {
+                                self.focus(window);
+                                self.layout_manager.borrow().sync(self);
+                            }
 
                             trace!("Event MAP_REQUEST triggered");
                         }
                         xcb::UNMAP_NOTIFY => {
+                            let unmap_notify_event: &xcb::UnmapNotifyEvent =
+                                unsafe { xcb::cast_event(&event) };
+
+                            // The same unmap is also reported to the window itself
+                            let window = unmap_notify_event.window();
+                            let known = self.layout_manager.borrow().is_managed(window)
+                                || self.docks.borrow().contains(&window);
+                            if unmap_notify_event.event() == self.window_id
+                                && !self.consume_ignore_unmap(window)
+                                && known
+                            {
+                                self.unmanage_window(window);
+                                self.set_wm_state(window, WM_STATE_WITHDRAWN);
+                                self.layout_manager.borrow_mut().recalc();
+                                self.layout_manager.borrow().sync(self);
+                            }
                             trace!("Event UNMAP_NOTIFY triggered");
                         }
                         xcb::CIRCULATE_REQUEST => {
                             trace!("Event CIRCULATE_REQUEST triggered");
                         }
                         xcb::CONFIGURE_REQUEST => {
+                            let configure_request_event: &xcb::ConfigureRequestEvent =
+                                unsafe { xcb::cast_event(&event) };
+
+                            self.handle_configure_request(configure_request_event);
+                            self.flush();
                             trace!("Event CONFIGURE_REQUEST triggered");
                         }
                         xcb::CIRCULATE_NOTIFY => {
                             trace!("Event CIRCULATE_NOTIFY triggered");
                         }
+                        xcb::EXPOSE => {
+                            let expose_event: &xcb::ExposeEvent =
+                                unsafe { xcb::cast_event(&event) };
+
+                            // Redraw once the last of the exposed rectangles arrives
+                            if expose_event.count() == 0 {
+                                let window = expose_event.window();
+                                for bar in self.bars.borrow().iter() {
+                                    if bar.get_window() == window {
+                                        bar.draw(self);
+                                    }
+                                }
+                                self.flush();
+                            }
+                            trace!("Event EXPOSE triggered");
+                        }
                         xcb::CONFIGURE_NOTIFY => {
                             trace!("Event CONFIGURE_NOTIFY triggered");
                         }
@@ -278,7 +436,7 @@ impl Oscillator {
                                 unsafe { xcb::cast_event(&event) };
 
                             let window = destroy_notify_event.window();
-                            self.layout_manager.borrow_mut().unmanage(window);
+                            self.unmanage_window(window);
                             self.layout_manager.borrow_mut().recalc();
                             self.layout_manager.borrow().sync(self);
                             trace!("Event DESTROY_NOTIFY triggered");
@@ -293,8 +451,83 @@ impl Oscillator {
                             trace!("Event REPARENT_NOTIFY triggered");
                         }
                         xcb::PROPERTY_NOTIFY => {
+                            let property_notify_event: &xcb::PropertyNotifyEvent =
+                                unsafe { xcb::cast_event(&event) };
+
+                            let window = property_notify_event.window();
+                            let atom = property_notify_event.atom();
+                            if (atom == self.atoms.net_wm_strut
+                                || atom == self.atoms.net_wm_strut_partial)
+                                && self.docks.borrow().contains(&window)
+                            {
+                                self.update_strut();
+                                self.layout_manager.borrow_mut().recalc();
+                                self.layout_manager.borrow().sync(self);
+                            }
+                            // _NET_WM_STATE is written by oscillator itself, clients change it
+                            // through client messages
+                            if atom == xcb::ATOM_WM_HINTS
+                                && self.layout_manager.borrow().is_managed(window)
+                            {
+                                self.update_urgency(window);
+                            }
+                            if (atom == xcb::ATOM_WM_NAME || atom == self.atoms.net_wm_name)
+                                && self.layout_manager.borrow().is_managed(window)
+                            {
+                                let (applied, _) = self.apply_rules(window, true);
+                                if applied {
+                                    self.layout_manager.borrow_mut().recalc();
+                                    self.layout_manager.borrow().sync(self);
+                                    self.draw_bars();
+                                } else if self.layout_manager.borrow().get_focused_window()
+                                    == Some(window)
+                                {
+                                    self.draw_bars();
+                                }
+                            }
+                            // xsetroot -name sets the status when there is no status command
+                            if window == self.window_id
+                                && atom == xcb::ATOM_WM_NAME
+                                && self.status_receiver.is_none()
+                            {
+                                *self.status.borrow_mut() = self.get_string_property(
+                                    window,
+                                    xcb::ATOM_WM_NAME,
+                                    xcb::ATOM_ANY,
+                                );
+                                self.draw_bars();
+                                self.flush();
+                            }
+                            if atom == xcb::ATOM_WM_NORMAL_HINTS
+                                && self.layout_manager.borrow().is_managed(window)
+                            {
+                                self.layout_manager
+                                    .borrow_mut()
+                                    .set_size_hints(window, self.get_size_hints(window));
+                                self.layout_manager.borrow_mut().recalc();
+                                self.layout_manager.borrow().sync(self);
+                            }
                             trace!("Event PROPERTY_NOTIFY triggered");
                         }
+                        xcb::MAPPING_NOTIFY => {
+                            let mapping_notify_event: &xcb::MappingNotifyEvent =
+                                unsafe { xcb::cast_event(&event) };
+
+                            let request = mapping_notify_event.request();
+                            if request == xcb::MAPPING_KEYBOARD as u8
+                                || request == xcb::MAPPING_MODIFIER as u8
+                            {
+                                self.grab_keys();
+                                self.grab_buttons();
+                                self.flush();
+                            }
+                            trace!("Event MAPPING_NOTIFY triggered");
+                        }
+                        r if self.is_randr_event(r) => {
+                            self.update_monitors();
+                            self.flush();
+                            trace!("Event RANDR_NOTIFY triggered");
+                        }
                         0 => {
                             let error_message: &xcb::GenericError =
                                 unsafe { xcb::cast_event(&event) };
@@ -314,79 +547,913 @@ impl Oscillator {
         }
     }
 
-    pub fn get_screen(&self) -> xcb::base::StructPtr<'_, xcb::ffi::xproto::xcb_screen_t> {
-        return self
-            .connection
-            .get_setup()
-            .roots()
-            .nth(self.screen_num as usize)
-            .unwrap();
+    // Run the action bound to a key or a mouse button
+    pub fn handle_key(&self, key: &Key) {
+        match key {
+            Key::Spawn { command } => {
+                info!("Spawn: \"{}\"", command.join(" "));
+                let mut proc = std::process::Command::new(&command[0]);
+                for arg in 1..command.len() {
+                    proc.arg(&command[arg]);
+                }
+                match proc.spawn() {
+                    Err(_) => {
+                        info!("Spawn Failed"); //TODO: More detailed spawn failed information
+                    }
+                    _ => info!("Spawn \"{}\" successful", command.join(" ")),
+                }
+            }
+            Key::Quit => {
+                info!("Quit focus window");
+
+                let window = xcb::get_input_focus(&self.connection)
+                    .get_reply()
+                    .unwrap()
+                    .focus();
+                self.close_window(window);
+                self.flush();
+            }
+            Key::ForceKill => {
+                info!("Kill focus window");
+
+                let window = xcb::get_input_focus(&self.connection)
+                    .get_reply()
+                    .unwrap()
+                    .focus();
+                xcb::kill_client(&self.connection, window);
+                self.flush();
+            }
+            Key::SelTag { tag } => {
+                info!("Select Tag: \"{}\"", tag);
+
+                self.select_tag(*tag);
+                self.flush();
+            }
+            Key::TagTarget { tag } => {
+                let focused = self.layout_manager.borrow().get_focused_window();
+                if let Some(window) = focused {
+                    self.move_window_to_tag(window, *tag);
+                }
+
+                self.flush();
+            }
+            Key::ToggleBar => {
+                info!("Toggle Bar");
+
+                self.toggle_bar();
+                self.flush();
+            }
+            Key::ToggleFloating => {
+                info!("Toggle Floating");
+
+                self.layout_manager.borrow_mut().toggle_floating();
+                self.update_client_list();
+                self.layout_manager.borrow_mut().recalc();
+                self.layout_manager.borrow().sync(self);
+
+                self.flush();
+            }
+            Key::ToggleFullscreen => {
+                info!("Toggle Fullscreen");
+
+                let focused = self.layout_manager.borrow().get_focused_window();
+                if let Some(window) = focused {
+                    let fullscreen = self.layout_manager.borrow().is_fullscreen(window);
+                    self.set_fullscreen(window, !fullscreen);
+                }
+
+                self.flush();
+            }
+            Key::FocusUrgent => {
+                info!("Focus Urgent");
+
+                let urgent = self.layout_manager.borrow().get_latest_urgent();
+                if let Some(window) = urgent {
+                    self.show_and_focus(window);
+                }
+
+                self.flush();
+            }
+            Key::IncMasterRatio { delta } => {
+                info!("Increase master ratio by {}", delta);
+
+                self.layout_manager.borrow_mut().inc_master_ratio(*delta);
+                self.layout_manager.borrow_mut().recalc();
+                self.layout_manager.borrow().sync(self);
+
+                self.flush();
+            }
+            Key::IncMasterCount { delta } => {
+                info!("Increase master count by {}", delta);
+
+                self.layout_manager.borrow_mut().inc_master_count(*delta);
+                self.layout_manager.borrow_mut().recalc();
+                self.layout_manager.borrow().sync(self);
+
+                self.flush();
+            }
+            Key::NextLayout => {
+                info!("Next Layout");
+
+                self.layout_manager.borrow_mut().next_layout();
+                self.layout_manager.borrow_mut().recalc();
+                self.layout_manager.borrow().sync(self);
+
+                self.flush();
+            }
+            Key::SetLayout { name } => {
+                info!("Set Layout: \"{}\"", name);
+
+                self.layout_manager.borrow_mut().set_layout(name);
+                self.layout_manager.borrow_mut().recalc();
+                self.layout_manager.borrow().sync(self);
+
+                self.flush();
+            }
+            Key::SelAllTag => {
+                info!("Select All Tags");
+
+                let current_tag = self.current_tag();
+                current_tag.borrow_mut().clear();
+                for i in 0..TAG_COUNT {
+                    current_tag.borrow_mut().insert(i);
+                }
+                self.update_current_desktop();
+
+                self.draw_bars();
+                self.layout_manager.borrow_mut().recalc();
+                self.layout_manager.borrow().sync(self);
+
+                self.flush();
+            }
+            Key::FocusMonitor { delta } => {
+                info!("Focus monitor by {}", delta);
+
+                self.focus_monitor(self.relative_monitor(*delta));
+                self.flush();
+            }
+            Key::MonitorTarget { delta } => {
+                let focused = self.layout_manager.borrow().get_focused_window();
+                if let Some(window) = focused {
+                    let monitor = self.relative_monitor(*delta);
+                    self.move_window_to_monitor(window, monitor);
+                }
+
+                self.flush();
+            }
+        }
+    }
+
+    // Prefix the name of a key or a button with the pressed modifiers, like "M4-S-Return"
+    fn binding_string(&self, state: u16, name: &str) -> String {
+        let keymod = state & !self.lock_mask.get();
+        let mut binding = name.to_string();
+        for i in 0..8 {
+            if keymod & 1 << (7 - i) > 0 {
+                binding = format!("{}-{}", MOD_MAP[&(1 << (7 - i))], binding);
+            }
+        }
+        binding
+    }
+
+    // Tags shown on the focused monitor
+    pub fn current_tag(&self) -> Arc<RefCell<HashSet<u32>>> {
+        let layout_manager = self.layout_manager.borrow();
+        layout_manager.get_current_tag(layout_manager.get_focused_monitor())
+    }
+
+    // Show or hide the tag on the focused monitor, at least one tag is kept
+    pub fn toggle_tag(&self, tag: u32) {
+        let current_tag = self.current_tag();
+        if !current_tag.borrow_mut().remove(&tag) {
+            current_tag.borrow_mut().insert(tag);
+        } else if current_tag.borrow().is_empty() {
+            current_tag.borrow_mut().insert(tag);
+            return;
+        }
+        self.update_current_desktop();
+
+        self.draw_bars();
+        self.layout_manager.borrow_mut().recalc();
+        self.layout_manager.borrow().sync(self);
+    }
+
+    // On a tag: left click views it, right click toggles it and modifier click moves the focused
+    // window to it. Clicks on the title and status run the bound actions.
+    fn handle_bar_click(&self, event: &xcb::ButtonPressEvent) {
+        let x = event.root_x() as i32;
+        let y = event.root_y() as i32;
+        let (monitor, region) = match self.bars.borrow().iter().find_map(|bar| {
+            bar.region_at(x, y)
+                .map(|region| (bar.get_monitor(), region))
+        }) {
+            Some(click) => click,
+            None => return,
+        };
+        self.focus_monitor(monitor);
+
+        let button = event.detail();
+        let binding = self.binding_string(event.state() & 0xff, &format!("Button{}", button));
+        info!("Click {} on bar of monitor {}", binding, monitor);
+        let moving = parse_modifier(self.settings.get_mouse_modifier())
+            .is_some_and(|modifier| event.state() & modifier == modifier);
+        match region {
+            BarRegion::Tag(tag) if moving => {
+                let focused = self.layout_manager.borrow().get_focused_window();
+                if let Some(window) = focused {
+                    self.move_window_to_tag(window, tag);
+                }
+            }
+            BarRegion::Tag(tag) if button == xcb::BUTTON_INDEX_1 as u8 => self.select_tag(tag),
+            BarRegion::Tag(tag) if button == xcb::BUTTON_INDEX_3 as u8 => self.toggle_tag(tag),
+            BarRegion::Tag(_) => {}
+            BarRegion::Title => {
+                if let Some(key) = self
+                    .settings
+                    .get_title_buttons()
+                    .get(&binding.to_lowercase())
+                {
+                    self.handle_key(key);
+                }
+            }
+            BarRegion::Status => {
+                if let Some(key) = self
+                    .settings
+                    .get_status_buttons()
+                    .get(&binding.to_lowercase())
+                {
+                    self.handle_key(key);
+                }
+            }
+        }
+    }
+
+    pub fn select_tag(&self, tag: u32) {
+        let current_tag = self.current_tag();
+        current_tag.borrow_mut().clear();
+        current_tag.borrow_mut().insert(tag);
+        self.update_current_desktop();
+
+        self.draw_bars();
+        self.layout_manager.borrow_mut().recalc();
+        self.layout_manager.borrow().sync(self);
+    }
+
+    pub fn move_window_to_tag(&self, window: u32, tag: u32) {
+        info!("Move window {} to Tag: \"{}\"", window, tag);
+
+        let mut tags = HashSet::new();
+        tags.insert(tag);
+        self.layout_manager.borrow_mut().set_tags(window, tags);
+        self.publish_window_desktop(window);
+        for transient in self.layout_manager.borrow().get_transients(window) {
+            self.publish_window_desktop(transient);
+        }
+
+        self.layout_manager.borrow_mut().recalc();
+        self.layout_manager.borrow().sync(self);
     }
 
+    // Decide how to treat a window asking to be mapped, return true if it should be focused
+    pub fn handle_new_window(&self, window: u32) -> bool {
+        let managed = self.layout_manager.borrow().is_managed(window);
+        let parent = self
+            .get_transient_for(window)
+            .filter(|parent| self.layout_manager.borrow().is_managed(*parent));
+
+        let focus = match self.get_window_kind(window) {
+            WindowKind::Dock => {
+                self.manage_dock(window);
+                false
+            }
+            WindowKind::Unmanaged => {
+                info!("Map unmanaged window {}", window);
+                xcb::map_window(&self.connection, window);
+                false
+            }
+            _ if parent.is_some() => {
+                // Transient windows follow their parent
+                self.manage_window(window);
+                if !managed {
+                    let parent = parent.unwrap();
+                    info!("Window {} is transient for {}", window, parent);
+                    self.layout_manager
+                        .borrow_mut()
+                        .set_transient_for(window, parent);
+                    self.float_over_parent(window);
+                }
+                true
+            }
+            WindowKind::Floating => {
+                self.manage_window(window);
+                if !managed {
+                    self.float_over_parent(window);
+                }
+                false
+            }
+            WindowKind::Normal => {
+                self.manage_window(window);
+                false
+            }
+        };
+
+        if !managed && self.layout_manager.borrow().is_managed(window) {
+            // A window may be mapped already urgent or fullscreen
+            let states = self.get_net_wm_state(window);
+            if states.contains(&self.atoms.net_wm_state_demands_attention) {
+                self.demands_attention.borrow_mut().insert(window);
+            }
+            self.update_urgency(window);
+            if states.contains(&self.atoms.net_wm_state_fullscreen) {
+                self.set_fullscreen(window, true);
+            }
+            if let (_, Some(rule_focus)) = self.apply_rules(window, false) {
+                return rule_focus;
+            }
+        }
+        focus
+    }
+
+    pub fn get_window_properties(&self, window: u32) -> WindowProperties {
+        // WM_CLASS holds the instance and the class, each ending with a NUL
+        let (instance, class) = match xcb::get_property(
+            &self.connection,
+            false,
+            window,
+            xcb::ATOM_WM_CLASS,
+            xcb::ATOM_STRING,
+            0,
+            256,
+        )
+        .get_reply()
+        {
+            Ok(reply) => {
+                let value = String::from_utf8_lossy(reply.value::<u8>()).into_owned();
+                let mut parts = value.split('\0');
+                (
+                    parts.next().unwrap_or("").to_string(),
+                    parts.next().unwrap_or("").to_string(),
+                )
+            }
+            Err(_) => (String::new(), String::new()),
+        };
+
+        WindowProperties {
+            class,
+            instance,
+            title: self.get_window_title(window),
+            role: self.get_string_property(window, self.atoms.wm_window_role, xcb::ATOM_STRING),
+        }
+    }
+
+    // Title of the focused window if it is shown on the monitor
+    pub fn get_focused_title(&self, monitor: usize) -> Option<String> {
+        let window = self.layout_manager.borrow().get_focused_window()?;
+        if !self.layout_manager.borrow().is_visible(window)
+            || self.layout_manager.borrow().get_monitor(window) != Some(monitor)
+        {
+            return None;
+        }
+        Some(self.get_window_title(window))
+    }
+
+    // Prefer _NET_WM_NAME and fall back to WM_NAME
+    pub fn get_window_title(&self, window: u32) -> String {
+        let title =
+            self.get_string_property(window, self.atoms.net_wm_name, self.atoms.utf8_string);
+        if !title.is_empty() {
+            return title;
+        }
+        self.get_string_property(window, xcb::ATOM_WM_NAME, xcb::ATOM_ANY)
+    }
+
+    fn get_string_property(&self, window: u32, property: xcb::Atom, type_: xcb::Atom) -> String {
+        match xcb::get_property(&self.connection, false, window, property, type_, 0, 1024)
+            .get_reply()
+        {
+            Ok(reply) => String::from_utf8_lossy(reply.value::<u8>()).into_owned(),
+            Err(_) => String::new(),
+        }
+    }
+
+    // Apply every matching rule, return whether any rule applied and the focus decision of
+    // the last one which has it
+    pub fn apply_rules(&self, window: u32, title_change: bool) -> (bool, Option<bool>) {
+        let properties = self.get_window_properties(window);
+        let settings = self.settings.clone();
+        let mut applied = false;
+        let mut focus = None;
+        for rule in settings.get_rules() {
+            if (title_change && !rule.on_title_change) || !rule.matches(&properties) {
+                continue;
+            }
+            info!("Apply rule to window {}", window);
+            applied = true;
+
+            if let Some(tags) = &rule.tags {
+                let tags: HashSet<u32> = tags
+                    .iter()
+                    .filter(|tag| **tag < TAG_COUNT)
+                    .cloned()
+                    .collect();
+                self.layout_manager.borrow_mut().set_tags(window, tags);
+                self.publish_window_desktop(window);
+            }
+            if let Some(floating) = rule.floating {
+                if floating && !self.layout_manager.borrow().is_floating(window) {
+                    // Like dialogs, at the center of the parent or the monitor
+                    self.float_over_parent(window);
+                } else {
+                    self.layout_manager
+                        .borrow_mut()
+                        .set_floating(window, floating);
+                }
+            }
+            if let Some(border) = rule.border {
+                self.layout_manager
+                    .borrow_mut()
+                    .set_border_override(window, border);
+            }
+            if let Some(fullscreen) = rule.fullscreen {
+                self.set_fullscreen(window, fullscreen);
+            }
+            if let Some(monitor) = rule.monitor {
+                if (monitor as usize) < self.layout_manager.borrow().monitor_count() {
+                    self.layout_manager
+                        .borrow_mut()
+                        .set_monitor(window, monitor as usize);
+                    self.publish_window_desktop(window);
+                } else {
+                    warn!("Monitor {} does not exist", monitor);
+                }
+            }
+            if rule.focus.is_some() {
+                focus = rule.focus;
+            }
+        }
+        (applied, focus)
+    }
+
+    pub fn get_transient_for(&self, window: u32) -> Option<u32> {
+        let reply = xcb::get_property(
+            &self.connection,
+            false,
+            window,
+            xcb::ATOM_WM_TRANSIENT_FOR,
+            xcb::ATOM_WINDOW,
+            0,
+            1,
+        )
+        .get_reply()
+        .ok()?;
+        reply
+            .value::<u32>()
+            .first()
+            .cloned()
+            .filter(|parent| *parent != xcb::NONE)
+    }
+
+    // Switch to the tag of the window on its monitor if it is hidden, then focus it
+    pub fn show_and_focus(&self, window: u32) {
+        let monitor = self.layout_manager.borrow().get_monitor(window);
+        if let Some(monitor) = monitor {
+            self.layout_manager.borrow_mut().focus_monitor(monitor);
+        }
+        if !self.layout_manager.borrow().is_visible(window) {
+            let tag = self
+                .layout_manager
+                .borrow()
+                .get_tags(window)
+                .and_then(|tags| tags.iter().cloned().min());
+            if let Some(tag) = tag {
+                self.select_tag(tag);
+            }
+        }
+        self.focus(window);
+        self.layout_manager.borrow().sync(self);
+    }
+
+    // Urgency comes from the urgency hint in WM_HINTS or _NET_WM_STATE_DEMANDS_ATTENTION
+    pub fn update_urgency(&self, window: u32) {
+        let hinted = self
+            .get_wm_hints(window)
+            .first()
+            .is_some_and(|flags| flags & URGENCY_HINT > 0);
+        let demands_attention = self.demands_attention.borrow().contains(&window);
+
+        let focused = self.layout_manager.borrow().get_focused_window() == Some(window);
+        if focused {
+            if hinted {
+                self.clear_urgency_hint(window);
+            }
+            self.demands_attention.borrow_mut().remove(&window);
+        }
+        self.set_urgent(window, (hinted || demands_attention) && !focused);
+    }
+
+    fn get_wm_hints(&self, window: u32) -> Vec<u32> {
+        match xcb::get_property(
+            &self.connection,
+            false,
+            window,
+            xcb::ATOM_WM_HINTS,
+            xcb::ATOM_WM_HINTS,
+            0,
+            9,
+        )
+        .get_reply()
+        {
+            Ok(reply) => reply.value::<u32>().to_vec(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    // Remove XUrgencyHint from WM_HINTS, the client has got the attention it asked for
+    fn clear_urgency_hint(&self, window: u32) {
+        let mut hints = self.get_wm_hints(window);
+        match hints.first_mut() {
+            Some(flags) if *flags & URGENCY_HINT > 0 => *flags &= !URGENCY_HINT,
+            _ => return,
+        }
+        xcb::change_property(
+            &self.connection,
+            xcb::PROP_MODE_REPLACE as u8,
+            window,
+            xcb::ATOM_WM_HINTS,
+            xcb::ATOM_WM_HINTS,
+            32,
+            &hints,
+        );
+    }
+
+    pub fn set_urgent(&self, window: u32, urgent: bool) {
+        if self.layout_manager.borrow().is_urgent(window) == urgent {
+            return;
+        }
+        info!("Set window {} urgent: {}", window, urgent);
+        self.layout_manager.borrow_mut().set_urgent(window, urgent);
+        self.update_wm_state(window);
+        self.draw_bars();
+    }
+
+    // Keep the newest line printed by the status command
+    fn receive_status(&self) {
+        if let Some(receiver) = &self.status_receiver {
+            if let Some(line) = receiver.try_iter().last() {
+                if *self.status.borrow() == line {
+                    return;
+                }
+                *self.status.borrow_mut() = line;
+                self.draw_bars();
+            }
+        }
+    }
+
+    pub fn get_status(&self) -> String {
+        self.status.borrow().clone()
+    }
+
+    pub fn get_urgent_tags(&self, monitor: usize) -> HashSet<u32> {
+        self.layout_manager.borrow().get_urgent_tags(monitor)
+    }
+
+    pub fn manage_window(&self, window: u32) {
+        if !self.layout_manager.borrow().is_managed(window) {
+            self.layout_manager.borrow_mut().manage(window);
+            if let Ok(geometry) = xcb::get_geometry(&self.connection, window).get_reply() {
+                self.layout_manager.borrow_mut().set_geometry(
+                    window,
+                    Geometry::new(
+                        geometry.x().max(0) as u32,
+                        geometry.y().max(0) as u32,
+                        geometry.width() as u32,
+                        geometry.height() as u32,
+                    ),
+                );
+            }
+        }
+        self.layout_manager
+            .borrow_mut()
+            .set_size_hints(window, self.get_size_hints(window));
+        self.listen_window_event(window);
+        self.publish_window_desktop(window);
+        self.update_client_list();
+    }
+
+    pub fn unmanage_window(&self, window: u32) {
+        self.layout_manager.borrow_mut().unmanage(window);
+        self.shown_windows.borrow_mut().remove(&window);
+        self.ignore_unmap.borrow_mut().remove(&window);
+        self.demands_attention.borrow_mut().remove(&window);
+        if self.docks.borrow_mut().remove(&window) {
+            self.update_strut();
+        }
+        self.update_client_list();
+        self.draw_bars();
+    }
+
+    // Return true if the unmap of the window is caused by oscillator
+    fn consume_ignore_unmap(&self, window: u32) -> bool {
+        let mut ignore_unmap = self.ignore_unmap.borrow_mut();
+        match ignore_unmap.get_mut(&window) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn set_wm_state(&self, window: u32, state: u32) {
+        xcb::change_property(
+            &self.connection,
+            xcb::PROP_MODE_REPLACE as u8,
+            window,
+            self.atoms.wm_state,
+            self.atoms.wm_state,
+            32,
+            &[state, xcb::NONE],
+        );
+    }
+
+    pub fn get_wm_state(&self, window: u32) -> Option<u32> {
+        let reply = xcb::get_property(
+            &self.connection,
+            false,
+            window,
+            self.atoms.wm_state,
+            self.atoms.wm_state,
+            0,
+            2,
+        )
+        .get_reply()
+        .ok()?;
+        reply.value::<u32>().first().copied()
+    }
+
+    // Manage windows which are already mapped or iconic before oscillator starts
+    fn adopt_windows(&self) {
+        let tree = match xcb::query_tree(&self.connection, self.window_id).get_reply() {
+            Ok(tree) => tree,
+            Err(_) => return,
+        };
+
+        for window in tree.children() {
+            let attributes = match xcb::get_window_attributes(&self.connection, *window).get_reply()
+            {
+                Ok(attributes) => attributes,
+                Err(_) => continue,
+            };
+            // Iconic windows were hidden by a previous window manager, e.g. on another tag
+            let viewable = attributes.map_state() == xcb::MAP_STATE_VIEWABLE as u8;
+            if attributes.override_redirect()
+                || !(viewable || self.get_wm_state(*window) == Some(WM_STATE_ICONIC))
+            {
+                continue;
+            }
+
+            info!("Adopt window {}", window);
+            self.handle_new_window(*window);
+            if !self.layout_manager.borrow().is_managed(*window) {
+                continue;
+            }
+            if viewable {
+                self.shown_windows.borrow_mut().insert(*window);
+            }
+            // Keep the window on the monitor it is shown on
+            if let Ok(geometry) = xcb::get_geometry(&self.connection, *window).get_reply() {
+                let monitor = self.layout_manager.borrow().monitor_at(
+                    (geometry.x() as i32 + geometry.width() as i32 / 2).max(0) as u32,
+                    (geometry.y() as i32 + geometry.height() as i32 / 2).max(0) as u32,
+                );
+                self.layout_manager
+                    .borrow_mut()
+                    .set_monitor(*window, monitor);
+            }
+            if let Some(tags) = self.get_window_desktop(*window) {
+                self.layout_manager.borrow_mut().set_tags(*window, tags);
+                self.publish_window_desktop(*window);
+            }
+        }
+
+        self.layout_manager.borrow_mut().recalc();
+        self.layout_manager.borrow().sync(self);
+    }
+
+    // Read tags of the window from _NET_WM_DESKTOP, 0xFFFFFFFF means all tags
+    pub fn get_window_desktop(&self, window: u32) -> Option<HashSet<u32>> {
+        let reply = xcb::get_property(
+            &self.connection,
+            false,
+            window,
+            self.atoms.net_wm_desktop,
+            xcb::ATOM_CARDINAL,
+            0,
+            1,
+        )
+        .get_reply()
+        .ok()?;
+        let desktop = *reply.value::<u32>().first()?;
+
+        let mut tags = HashSet::new();
+        if desktop == 0xFFFF_FFFF {
+            for i in 0..TAG_COUNT {
+                tags.insert(i);
+            }
+        } else if desktop < TAG_COUNT {
+            tags.insert(desktop);
+        } else {
+            return None;
+        }
+        Some(tags)
+    }
+
+    // Save the tag of the window in _NET_WM_DESKTOP, so it can be restored after restart
+    pub fn publish_window_desktop(&self, window: u32) {
+        let tags = match self.layout_manager.borrow().get_tags(window) {
+            Some(tags) => tags,
+            None => return,
+        };
+        let desktop = if tags.len() >= TAG_COUNT as usize {
+            0xFFFF_FFFF
+        } else {
+            tags.iter().cloned().min().unwrap_or(0)
+        };
+        xcb::change_property(
+            &self.connection,
+            xcb::PROP_MODE_REPLACE as u8,
+            window,
+            self.atoms.net_wm_desktop,
+            xcb::ATOM_CARDINAL,
+            32,
+            &[desktop],
+        );
+    }
+
+    fn handle_configure_request(&self, event: &xcb::ConfigureRequestEvent) {
+        let window = event.window();
+        let value_mask = event.value_mask();
+
+        if !self.layout_manager.borrow().is_managed(window) {
+            // Unmanaged windows can configure themselves freely
+            let mut values = Vec::new();
+            if value_mask & xcb::CONFIG_WINDOW_X as u16 > 0 {
+                values.push((xcb::CONFIG_WINDOW_X as u16, event.x() as i32 as u32));
+            }
+            if value_mask & xcb::CONFIG_WINDOW_Y as u16 > 0 {
+                values.push((xcb::CONFIG_WINDOW_Y as u16, event.y() as i32 as u32));
+            }
+            if value_mask & xcb::CONFIG_WINDOW_WIDTH as u16 > 0 {
+                values.push((xcb::CONFIG_WINDOW_WIDTH as u16, event.width() as u32));
+            }
+            if value_mask & xcb::CONFIG_WINDOW_HEIGHT as u16 > 0 {
+                values.push((xcb::CONFIG_WINDOW_HEIGHT as u16, event.height() as u32));
+            }
+            if value_mask & xcb::CONFIG_WINDOW_BORDER_WIDTH as u16 > 0 {
+                values.push((
+                    xcb::CONFIG_WINDOW_BORDER_WIDTH as u16,
+                    event.border_width() as u32,
+                ));
+            }
+            if value_mask & xcb::CONFIG_WINDOW_SIBLING as u16 > 0 {
+                values.push((xcb::CONFIG_WINDOW_SIBLING as u16, event.sibling()));
+            }
+            if value_mask & xcb::CONFIG_WINDOW_STACK_MODE as u16 > 0 {
+                values.push((
+                    xcb::CONFIG_WINDOW_STACK_MODE as u16,
+                    event.stack_mode() as u32,
+                ));
+            }
+            info!("Forward configure request of unmanaged window {}", window);
+            xcb::configure_window(&self.connection, window, &values);
+            return;
+        }
+
+        if self.layout_manager.borrow().is_floating(window) {
+            let mut geometry = self.layout_manager.borrow().get_geometry(window).unwrap();
+            if value_mask & xcb::CONFIG_WINDOW_X as u16 > 0 {
+                geometry.x = event.x().max(0) as u32;
+            }
+            if value_mask & xcb::CONFIG_WINDOW_Y as u16 > 0 {
+                geometry.y = event.y().max(0) as u32;
+            }
+            if value_mask & xcb::CONFIG_WINDOW_WIDTH as u16 > 0 {
+                geometry.width = event.width() as u32;
+            }
+            if value_mask & xcb::CONFIG_WINDOW_HEIGHT as u16 > 0 {
+                geometry.height = event.height() as u32;
+            }
+
+            self.layout_manager
+                .borrow_mut()
+                .set_floating_geometry(window, geometry);
+            let geometry = self.layout_manager.borrow().get_geometry(window).unwrap();
+            self.move_and_resize_window(
+                window,
+                geometry.x,
+                geometry.y,
+                geometry.width,
+                geometry.height,
+            );
+        }
+
+        // Tell the client where it really is
+        self.send_configure_notify(window);
+    }
+
+    pub fn send_configure_notify(&self, window: u32) {
+        let geometry = match self.layout_manager.borrow().get_geometry(window) {
+            Some(geometry) => geometry,
+            None => return,
+        };
+        let border = self.layout_manager.borrow().get_border(window);
+
+        let event = xcb::ConfigureNotifyEvent::new(
+            window,
+            window,
+            xcb::NONE,
+            geometry.x as i16,
+            geometry.y as i16,
+            geometry.width as u16,
+            geometry.height as u16,
+            border as u16,
+            false,
+        );
+        xcb::send_event(
+            &self.connection,
+            false,
+            window,
+            xcb::EVENT_MASK_STRUCTURE_NOTIFY,
+            &event,
+        );
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn draw_text(
         &self,
+        window: u32,
+        gc: u32,
         x: i32,
         y: i32,
         foreground: Color,
         background: Color,
-        font: u32,
         text: &str,
     ) {
-        let screen = self.get_screen();
-        let gc = self.connection.generate_id();
-        xcb::create_gc(
+        xcb::change_gc(
             &self.connection,
             gc,
-            screen.root(),
             &[
                 (xcb::GC_FOREGROUND, foreground.into()),
                 (xcb::GC_BACKGROUND, background.into()),
-                (xcb::GC_FONT, font),
             ],
         );
+        xcb::image_text_8(&self.connection, window, gc, x as i16, y as i16, text);
+    }
 
-        xcb::image_text_8(
+    #[allow(clippy::too_many_arguments)]
+    pub fn fill_rect(&self, window: u32, gc: u32, x: i32, y: i32, w: i32, h: i32, color: Color) {
+        xcb::change_gc(&self.connection, gc, &[(xcb::GC_FOREGROUND, color.into())]);
+        xcb::poly_fill_rectangle(
             &self.connection,
-            self.window_id,
+            window,
             gc,
-            x as i16,
-            y as i16,
-            text,
+            &[xcb::Rectangle::new(x as i16, y as i16, w as u16, h as u16)],
         );
     }
 
-    pub fn fill_rect(&self, x: i32, y: i32, w: i32, h: i32, color: Color) {
-        let screen = self
-            .connection
-            .get_setup()
-            .roots()
-            .nth(self.screen_num as usize)
-            .unwrap();
-        let foreground = self.connection.generate_id();
-
+    // A graphics context drawing on `drawable` with `font`, to be reused by draw_text and fill_rect
+    pub fn create_gc(&self, drawable: u32, font: u32) -> u32 {
+        let gc = self.connection.generate_id();
         xcb::create_gc(
             &self.connection,
-            foreground,
-            screen.root(),
-            &[
-                (xcb::GC_FOREGROUND, color.into()),
-                (xcb::GC_GRAPHICS_EXPOSURES, 0),
-            ],
-        );
-        xcb::poly_fill_rectangle(
-            &self.connection,
-            self.window_id,
-            foreground,
-            &[xcb::Rectangle::new(x as i16, y as i16, w as u16, h as u16)],
+            gc,
+            drawable,
+            &[(xcb::GC_FONT, font), (xcb::GC_GRAPHICS_EXPOSURES, 0)],
         );
+        gc
     }
 
     pub fn focus(&self, window: u32) {
         info!("Focus on WINDOW {}", window);
 
+        let urgent = self.layout_manager.borrow().is_urgent(window);
+        let monitor = self.layout_manager.borrow().get_focused_monitor();
         self.layout_manager.borrow_mut().focus(window);
+        self.demands_attention.borrow_mut().remove(&window);
+        if urgent {
+            self.clear_urgency_hint(window);
+            self.update_wm_state(window);
+        }
+        if self.layout_manager.borrow().get_focused_monitor() != monitor {
+            self.update_current_desktop();
+        }
+        self.draw_bars();
         self.layout_manager.borrow_mut().recalc();
+        if self.layout_manager.borrow().is_floating(window) {
+            self.raise_window(window);
+        }
         xcb::set_input_focus(&self.connection, 1, window, xcb::CURRENT_TIME);
+        self.update_active_window(window);
     }
 
     pub fn set_window_border(&self, window: u32, border_width: u32, border_color: Color) {
@@ -407,10 +1474,206 @@ impl Oscillator {
         );
     }
 
+    // Find which modifiers NumLock and ScrollLock are bound to, so they can be ignored
+    // together with CapsLock.
+    fn update_lock_mask(&self) {
+        let key_symbols = xcb_util::keysyms::KeySymbols::new(&self.connection);
+        let keycodes = |name: &str| -> Vec<xcb::Keycode> {
+            keysym_by_name(name)
+                .map(|keysym| key_symbols.get_keycode(keysym).collect())
+                .unwrap_or_default()
+        };
+        let num_lock = keycodes("Num_Lock");
+        let scroll_lock = keycodes("Scroll_Lock");
+
+        let modifier_mapping = xcb::get_modifier_mapping(&self.connection)
+            .get_reply()
+            .unwrap();
+        let per_modifier = modifier_mapping.keycodes_per_modifier() as usize;
+
+        let mut lock_mask = xcb::MOD_MASK_LOCK as u16;
+        for (index, keycode) in modifier_mapping.keycodes().iter().enumerate() {
+            if *keycode == 0 {
+                continue;
+            }
+            if num_lock.contains(keycode) || scroll_lock.contains(keycode) {
+                lock_mask |= 1 << (index / per_modifier);
+            }
+        }
+        self.lock_mask.set(lock_mask);
+    }
+
+    // Every combination of the lock modifiers has to be grabbed separately
+    fn lock_combinations(&self) -> Vec<u16> {
+        let lock_mask = self.lock_mask.get();
+        let mut lock_combinations = vec![0u16];
+        for bit in 0..8 {
+            if lock_mask & (1 << bit) > 0 {
+                for index in 0..lock_combinations.len() {
+                    let combination = lock_combinations[index] | (1 << bit);
+                    lock_combinations.push(combination);
+                }
+            }
+        }
+        lock_combinations
+    }
+
+    pub fn grab_keys(&self) {
+        xcb::ungrab_key(
+            &self.connection,
+            xcb::GRAB_ANY as u8,
+            self.window_id,
+            xcb::MOD_MASK_ANY as u16,
+        );
+        self.update_lock_mask();
+
+        let lock_combinations = self.lock_combinations();
+        let key_symbols = xcb_util::keysyms::KeySymbols::new(&self.connection);
+        for key in self.settings.get_keys().keys() {
+            let key_bind = match parse_key_string(key) {
+                Some(key_bind) => key_bind,
+                None => {
+                    warn!("Cannot parse KEY: {}", key);
+                    continue;
+                }
+            };
+
+            for keysym in &key_bind.keysyms {
+                for keycode in key_symbols.get_keycode(*keysym) {
+                    for lock in &lock_combinations {
+                        xcb::grab_key(
+                            &self.connection,
+                            true,
+                            self.window_id,
+                            key_bind.modifiers | lock,
+                            keycode,
+                            xcb::GRAB_MODE_ASYNC as u8,
+                            xcb::GRAB_MODE_ASYNC as u8,
+                        );
+                    }
+                }
+            }
+            info!("Grab KEY: {}", key);
+        }
+    }
+
+    // Modifier + button 1 moves a window, modifier + button 3 resizes it
+    pub fn grab_buttons(&self) {
+        xcb::ungrab_button(
+            &self.connection,
+            xcb::BUTTON_INDEX_ANY as u8,
+            self.window_id,
+            xcb::MOD_MASK_ANY as u16,
+        );
+
+        let modifier = match parse_modifier(self.settings.get_mouse_modifier()) {
+            Some(modifier) => modifier,
+            None => {
+                warn!(
+                    "Cannot parse mouse modifier: {}",
+                    self.settings.get_mouse_modifier()
+                );
+                return;
+            }
+        };
+
+        let lock_combinations = self.lock_combinations();
+        for button in &[xcb::BUTTON_INDEX_1, xcb::BUTTON_INDEX_3] {
+            for lock in &lock_combinations {
+                xcb::grab_button(
+                    &self.connection,
+                    false,
+                    self.window_id,
+                    (xcb::EVENT_MASK_BUTTON_PRESS
+                        | xcb::EVENT_MASK_BUTTON_RELEASE
+                        | xcb::EVENT_MASK_POINTER_MOTION) as u16,
+                    xcb::GRAB_MODE_ASYNC as u8,
+                    xcb::GRAB_MODE_ASYNC as u8,
+                    xcb::NONE,
+                    xcb::NONE,
+                    *button as u8,
+                    modifier | lock,
+                );
+            }
+        }
+    }
+
+    fn start_drag(&self, window: u32, button: u8, pointer_x: i32, pointer_y: i32) {
+        let geometry = match self.layout_manager.borrow().get_geometry(window) {
+            Some(geometry) => geometry,
+            None => return,
+        };
+        info!("Start dragging window {} with button {}", window, button);
+
+        self.focus(window);
+        self.layout_manager
+            .borrow_mut()
+            .set_floating_geometry(window, geometry);
+        self.layout_manager.borrow_mut().recalc();
+        self.layout_manager.borrow().sync(self);
+
+        let cursor = if button == xcb::BUTTON_INDEX_1 as u8 {
+            self.move_cursor
+        } else {
+            self.resize_cursor
+        };
+        xcb::grab_pointer(
+            &self.connection,
+            false,
+            self.window_id,
+            (xcb::EVENT_MASK_BUTTON_RELEASE | xcb::EVENT_MASK_POINTER_MOTION) as u16,
+            xcb::GRAB_MODE_ASYNC as u8,
+            xcb::GRAB_MODE_ASYNC as u8,
+            xcb::NONE,
+            cursor,
+            xcb::CURRENT_TIME,
+        );
+        self.flush();
+
+        *self.drag.borrow_mut() = Some(Drag {
+            window,
+            button,
+            pointer_x,
+            pointer_y,
+            geometry,
+        });
+    }
+
+    fn update_drag(&self, pointer_x: i32, pointer_y: i32) {
+        let (window, geometry) = match &*self.drag.borrow() {
+            Some(drag) => {
+                let dx = pointer_x - drag.pointer_x;
+                let dy = pointer_y - drag.pointer_y;
+                let mut geometry = drag.geometry;
+                if drag.button == xcb::BUTTON_INDEX_1 as u8 {
+                    geometry.x = (geometry.x as i32 + dx).max(0) as u32;
+                    geometry.y = (geometry.y as i32 + dy).max(0) as u32;
+                } else {
+                    geometry.width = (geometry.width as i32 + dx).max(1) as u32;
+                    geometry.height = (geometry.height as i32 + dy).max(1) as u32;
+                }
+                (drag.window, geometry)
+            }
+            None => return,
+        };
+
+        self.layout_manager
+            .borrow_mut()
+            .set_floating_geometry(window, geometry);
+        self.move_and_resize_window(
+            window,
+            geometry.x,
+            geometry.y,
+            geometry.width,
+            geometry.height,
+        );
+        self.flush();
+    }
+
     pub fn listen_window_event(&self, window: u32) {
-        const EVENT_MASK: u32 = xcb::EVENT_MASK_KEY_PRESS
-            | xcb::EVENT_MASK_ENTER_WINDOW
-            | xcb::EVENT_MASK_STRUCTURE_NOTIFY;
+        const EVENT_MASK: u32 = xcb::EVENT_MASK_ENTER_WINDOW
+            | xcb::EVENT_MASK_STRUCTURE_NOTIFY
+            | xcb::EVENT_MASK_PROPERTY_CHANGE;
 
         xcb::change_window_attributes(
             &self.connection,
@@ -419,14 +1682,90 @@ impl Oscillator {
         );
     }
 
+    pub fn raise_window(&self, window: u32) {
+        xcb::configure_window(
+            &self.connection,
+            window,
+            &[(xcb::CONFIG_WINDOW_STACK_MODE as u16, xcb::STACK_MODE_ABOVE)],
+        );
+    }
+
+    pub fn get_size_hints(&self, window: u32) -> SizeHints {
+        match xcb::get_property(
+            &self.connection,
+            false,
+            window,
+            xcb::ATOM_WM_NORMAL_HINTS,
+            xcb::ATOM_WM_SIZE_HINTS,
+            0,
+            18,
+        )
+        .get_reply()
+        {
+            Ok(reply) => SizeHints::from_raw(reply.value::<u32>()),
+            Err(_) => SizeHints::default(),
+        }
+    }
+
+    pub fn supports_protocol(&self, window: u32, protocol: xcb::Atom) -> bool {
+        match xcb::get_property(
+            &self.connection,
+            false,
+            window,
+            self.atoms.wm_protocols,
+            xcb::ATOM_ATOM,
+            0,
+            32,
+        )
+        .get_reply()
+        {
+            Ok(reply) => reply.value::<xcb::Atom>().contains(&protocol),
+            Err(_) => false,
+        }
+    }
+
+    pub fn send_protocol(&self, window: u32, protocol: xcb::Atom) {
+        let data = xcb::ClientMessageData::from_data32([protocol, xcb::CURRENT_TIME, 0, 0, 0]);
+        let event = xcb::ClientMessageEvent::new(32, window, self.atoms.wm_protocols, data);
+        xcb::send_event(
+            &self.connection,
+            false,
+            window,
+            xcb::EVENT_MASK_NO_EVENT,
+            &event,
+        );
+    }
+
+    // Ask the window to close itself, kill the client only if it doesn't support WM_DELETE_WINDOW
+    pub fn close_window(&self, window: u32) {
+        if window == self.window_id || window == xcb::NONE {
+            return;
+        }
+
+        if self.supports_protocol(window, self.atoms.wm_delete_window) {
+            info!("Send WM_DELETE_WINDOW to window {}", window);
+            self.send_protocol(window, self.atoms.wm_delete_window);
+        } else {
+            info!("Kill client of window {}", window);
+            xcb::kill_client(&self.connection, window);
+        }
+    }
+
     pub fn unmap_window(&self, window: u32) {
+        if !self.shown_windows.borrow_mut().remove(&window) {
+            return;
+        }
         info!("Unmap window {}", window);
+        *self.ignore_unmap.borrow_mut().entry(window).or_insert(0) += 1;
         xcb::unmap_window(&self.connection, window);
+        self.set_wm_state(window, WM_STATE_ICONIC);
     }
 
     pub fn map_window(&self, window: u32) {
         info!("Map window {}", window);
+        self.shown_windows.borrow_mut().insert(window);
         xcb::map_window(&self.connection, window);
+        self.set_wm_state(window, WM_STATE_NORMAL);
     }
 
     pub fn resize_window(&self, window: u32, width: u32, height: u32) {
@@ -558,6 +1897,46 @@ impl Oscillator {
         );
     }
 
+    // An override-redirect window of _NET_WM_WINDOW_TYPE_DOCK, redrawn on exposure
+    pub fn create_dock_window(&self, geometry: Geometry, background: Color) -> u32 {
+        let window = self.connection.generate_id();
+        xcb::create_window(
+            &self.connection,
+            xcb::COPY_FROM_PARENT as u8,
+            window,
+            self.window_id,
+            geometry.x as i16,
+            geometry.y as i16,
+            geometry.width as u16,
+            geometry.height as u16,
+            0,
+            xcb::WINDOW_CLASS_INPUT_OUTPUT as u16,
+            xcb::COPY_FROM_PARENT,
+            &[
+                (xcb::CW_BACK_PIXEL, background.into()),
+                (xcb::CW_OVERRIDE_REDIRECT, 1),
+                (
+                    xcb::CW_EVENT_MASK,
+                    xcb::EVENT_MASK_EXPOSURE | xcb::EVENT_MASK_BUTTON_PRESS,
+                ),
+            ],
+        );
+        xcb::change_property(
+            &self.connection,
+            xcb::PROP_MODE_REPLACE as u8,
+            window,
+            self.atoms.net_wm_window_type,
+            xcb::ATOM_ATOM,
+            32,
+            &[self.atoms.net_wm_window_type_dock],
+        );
+        window
+    }
+
+    pub fn get_screen_height(&self) -> u32 {
+        self.height.get() as u32
+    }
+
     pub fn create_font(&self, font_name: &str) -> u32 {
         let font = self.connection.generate_id();
         xcb::open_font(&self.connection, font, font_name);
@@ -566,14 +1945,11 @@ impl Oscillator {
     }
 
     pub fn query_text_extents(&self, font: u32, s: &str) -> TextExtends {
-        let len = s.len();
-        let ptr = s.as_ptr();
-        let ptr = ptr as *const xcb::Char2b;
-        let text_extends = xcb::query_text_extents(&self.connection, font, unsafe {
-            std::slice::from_raw_parts(ptr, len)
-        })
-        .get_reply()
-        .unwrap();
+        // Text is drawn with image_text_8, so every byte is a single character
+        let chars: Vec<xcb::Char2b> = s.bytes().map(|byte| xcb::Char2b::new(0, byte)).collect();
+        let text_extends = xcb::query_text_extents(&self.connection, font, &chars)
+            .get_reply()
+            .unwrap();
         return TextExtends {
             overall_width: text_extends.overall_width(),
             font_ascent: text_extends.font_ascent(),
@@ -0,0 +1,184 @@
+use crate::bar::Bar;
+use crate::layout_manager::Geometry;
+use crate::oscillator::Oscillator;
+
+impl Oscillator {
+    // Enabled CRTCs of RandR from left to right, mirrored outputs are counted once. The whole
+    // screen is one monitor if RandR is not available.
+    pub fn query_monitors(
+        connection: &xcb::Connection,
+        root: u32,
+        width: u32,
+        height: u32,
+    ) -> Vec<Geometry> {
+        let mut monitors: Vec<Geometry> = Vec::new();
+        if let Ok(resources) =
+            xcb::randr::get_screen_resources_current(connection, root).get_reply()
+        {
+            let cookies: Vec<_> = resources
+                .crtcs()
+                .iter()
+                .map(|crtc| {
+                    xcb::randr::get_crtc_info(connection, *crtc, resources.config_timestamp())
+                })
+                .collect();
+            for cookie in cookies {
+                let crtc = match cookie.get_reply() {
+                    Ok(crtc) => crtc,
+                    Err(_) => continue,
+                };
+                if crtc.num_outputs() == 0 || crtc.width() == 0 || crtc.height() == 0 {
+                    continue;
+                }
+                let geometry = Geometry::new(
+                    crtc.x().max(0) as u32,
+                    crtc.y().max(0) as u32,
+                    crtc.width() as u32,
+                    crtc.height() as u32,
+                );
+                if !monitors.contains(&geometry) {
+                    monitors.push(geometry);
+                }
+            }
+        }
+
+        if monitors.is_empty() {
+            monitors.push(Geometry::new(0, 0, width, height));
+        }
+        monitors.sort_by_key(|monitor| (monitor.x, monitor.y));
+        for (index, monitor) in monitors.iter().enumerate() {
+            info!(
+                "Monitor {}: X: {} Y: {} WIDTH: {} HEIGHT: {}",
+                index, monitor.x, monitor.y, monitor.width, monitor.height
+            );
+        }
+        monitors
+    }
+
+    // RRScreenChangeNotify and RRNotify, the latter carries CRTC changes
+    pub fn is_randr_event(&self, response_type: u8) -> bool {
+        match self.randr_event_base {
+            Some(base) => {
+                response_type == base + xcb::randr::SCREEN_CHANGE_NOTIFY
+                    || response_type == base + xcb::randr::NOTIFY
+            }
+            None => false,
+        }
+    }
+
+    // Rebuild monitors and bars after the screen is reconfigured
+    pub fn update_monitors(&self) {
+        if let Ok(geometry) = xcb::get_geometry(&self.connection, self.window_id).get_reply() {
+            self.width.set(geometry.width() as i32);
+            self.height.set(geometry.height() as i32);
+        }
+        let monitors = Oscillator::query_monitors(
+            &self.connection,
+            self.window_id,
+            self.width.get() as u32,
+            self.height.get() as u32,
+        );
+        if monitors == self.layout_manager.borrow().get_monitor_geometries() {
+            return;
+        }
+        info!("Monitors changed");
+
+        self.layout_manager.borrow_mut().set_monitors(&monitors);
+        // Bars are recreated at their new place, hidden bars stay hidden
+        let old_bars: Vec<Bar> = self.bars.borrow_mut().drain(..).collect();
+        for (index, monitor) in monitors.iter().enumerate() {
+            let mut bar = Bar::new(
+                self.settings.clone(),
+                index,
+                *monitor,
+                self.layout_manager.borrow().get_current_tag(index),
+            );
+            if let Some(old_bar) = old_bars.get(index) {
+                bar.hide_on_prepare(!old_bar.is_shown());
+            }
+            bar.prepare(self);
+            self.bars.borrow_mut().push(bar);
+        }
+        for bar in &old_bars {
+            bar.destroy(self);
+        }
+        self.update_bar_struts();
+
+        self.update_strut();
+        self.update_current_desktop();
+        self.layout_manager.borrow_mut().recalc();
+        self.layout_manager.borrow().sync(self);
+        self.draw_bars();
+    }
+
+    // The monitor `delta` steps away from the focused one, wrapping around
+    pub fn relative_monitor(&self, delta: i32) -> usize {
+        let layout_manager = self.layout_manager.borrow();
+        let count = layout_manager.monitor_count() as i32;
+        let focused = layout_manager.get_focused_monitor() as i32;
+        (focused + delta).rem_euclid(count) as usize
+    }
+
+    // Focus the first shown window of the monitor, or the root window if there is none
+    pub fn focus_monitor(&self, monitor: usize) {
+        if monitor == self.layout_manager.borrow().get_focused_monitor() {
+            return;
+        }
+        info!("Focus monitor {}", monitor);
+
+        self.layout_manager.borrow_mut().focus_monitor(monitor);
+        let window = self.layout_manager.borrow().get_shown_window(monitor);
+        self.focus(window.unwrap_or(self.window_id));
+        self.update_current_desktop();
+        self.layout_manager.borrow().sync(self);
+    }
+
+    pub fn move_window_to_monitor(&self, window: u32, monitor: usize) {
+        self.layout_manager
+            .borrow_mut()
+            .set_monitor(window, monitor);
+        self.publish_window_desktop(window);
+        for transient in self.layout_manager.borrow().get_transients(window) {
+            self.publish_window_desktop(transient);
+        }
+
+        self.draw_bars();
+        self.layout_manager.borrow_mut().recalc();
+        self.layout_manager.borrow().sync(self);
+    }
+
+    // Pointer entering an empty part of another monitor focuses it
+    pub fn update_monitor_focus(&self, pointer_x: i32, pointer_y: i32) {
+        let monitor = self
+            .layout_manager
+            .borrow()
+            .monitor_at(pointer_x.max(0) as u32, pointer_y.max(0) as u32);
+        self.focus_monitor(monitor);
+    }
+
+    pub fn update_bar_struts(&self) {
+        for bar in self.bars.borrow().iter() {
+            self.layout_manager
+                .borrow_mut()
+                .set_bar_strut(bar.get_monitor(), bar.reserved());
+        }
+    }
+
+    // Hide or show the bar of the focused monitor, the layout takes its space
+    pub fn toggle_bar(&self) {
+        let monitor = self.layout_manager.borrow().get_focused_monitor();
+        if let Some(bar) = self.bars.borrow().get(monitor) {
+            bar.set_shown(self, !bar.is_shown());
+        }
+        self.update_bar_struts();
+        self.update_workarea();
+        self.layout_manager.borrow_mut().recalc();
+        self.layout_manager.borrow().sync(self);
+    }
+
+    pub fn draw_bars(&self) {
+        for bar in self.bars.borrow().iter() {
+            bar.draw(self);
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use crate::setting::Rule;
+use regex::Regex;
+
+// Properties of a window which rules are matched on
+pub struct WindowProperties {
+    pub class: String,
+    pub instance: String,
+    pub title: String,
+    pub role: String,
+}
+
+impl Rule {
+    // Compile the title regex, so an invalid pattern is rejected when settings load
+    pub fn compile(&mut self) -> Result<(), regex::Error> {
+        self.title_regex = match &self.title {
+            Some(title) => Some(Regex::new(title)?),
+            None => None,
+        };
+        Ok(())
+    }
+
+    // Class, instance and role must be equal, title is a regex. Missing fields match anything
+    pub fn matches(&self, properties: &WindowProperties) -> bool {
+        if let Some(class) = &self.class {
+            if *class != properties.class {
+                return false;
+            }
+        }
+        if let Some(instance) = &self.instance {
+            if *instance != properties.instance {
+                return false;
+            }
+        }
+        if let Some(role) = &self.role {
+            if *role != properties.role {
+                return false;
+            }
+        }
+        if let Some(title) = &self.title_regex {
+            if !title.is_match(&properties.title) {
+                return false;
+            }
+        }
+        true
+    }
+}
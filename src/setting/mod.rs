@@ -1,3 +1,4 @@
+use regex::Regex;
 use std::collections::HashMap;
 use std::process;
 
@@ -8,17 +9,107 @@ pub enum Key {
     SelTag { tag: u32 },
     SelAllTag,
     TagTarget { tag: u32 },
+    ToggleFloating,
+    ToggleFullscreen,
+    FocusUrgent,
+    IncMasterRatio { delta: f32 },
+    IncMasterCount { delta: i32 },
+    NextLayout,
+    SetLayout { name: String },
+    // Monitors are counted from left to right, wrapping around
+    FocusMonitor { delta: i32 },
+    MonitorTarget { delta: i32 },
+    ToggleBar,
     Quit,
+    ForceKill,
 }
 
+// Rules are applied to windows matching all the given properties
 #[derive(Serialize, Deserialize)]
-#[serde(tag = "tiling_method")]
-pub enum LayoutManagerSettings {
-    Stack {
-        border: u32,
-        focus_border_color: String,
-        normal_border_color: String,
-    },
+pub struct Rule {
+    pub class: Option<String>,
+    pub instance: Option<String>,
+    pub title: Option<String>,
+    pub role: Option<String>,
+    // `title` compiled when settings load
+    #[serde(skip)]
+    pub title_regex: Option<Regex>,
+
+    pub tags: Option<Vec<u32>>,
+    pub floating: Option<bool>,
+    pub fullscreen: Option<bool>,
+    pub monitor: Option<u32>,
+    pub border: Option<u32>,
+    pub focus: Option<bool>,
+    // Apply the rule again when the title changes
+    #[serde(default)]
+    pub on_title_change: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum BarPosition {
+    Top,
+    Bottom,
+}
+
+// What to do when a client asks to be activated through _NET_ACTIVE_WINDOW
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum ActivationPolicy {
+    Focus,
+    Urgent,
+    Ignore,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct LayoutManagerSettings {
+    pub border: u32,
+    pub focus_border_color: String,
+    pub normal_border_color: String,
+    #[serde(default = "default_master_ratio")]
+    pub master_ratio: f32,
+    #[serde(default = "default_master_count")]
+    pub master_count: u32,
+    #[serde(default = "default_layouts")]
+    pub layouts: Vec<String>,
+}
+
+fn default_urgent_background_color() -> String {
+    "#bf616a".to_string()
+}
+
+fn default_urgent_font_color() -> String {
+    "#ffffff".to_string()
+}
+
+fn default_bar_position() -> BarPosition {
+    BarPosition::Top
+}
+
+fn default_mouse_modifier() -> String {
+    "M4".to_string()
+}
+
+fn default_activation_policy() -> ActivationPolicy {
+    ActivationPolicy::Focus
+}
+
+fn default_master_ratio() -> f32 {
+    0.5
+}
+
+fn default_master_count() -> u32 {
+    1
+}
+
+fn default_layouts() -> Vec<String> {
+    vec![
+        "stack".to_string(),
+        "monocle".to_string(),
+        "grid".to_string(),
+        "columns".to_string(),
+        "spiral".to_string(),
+        "centered_master".to_string(),
+    ]
 }
 
 #[derive(Serialize, Deserialize)]
@@ -32,21 +123,51 @@ pub struct BarSettings {
     pub tag_cell_width: u32,
     pub active_background_color: String,
     pub active_font_color: String,
+    #[serde(default = "default_urgent_background_color")]
+    pub urgent_background_color: String,
+    #[serde(default = "default_urgent_font_color")]
+    pub urgent_font_color: String,
+    // Command printing the status line by line, root WM_NAME is shown if it is not set
+    #[serde(default)]
+    pub status_command: Option<Vec<String>>,
+    #[serde(default = "default_bar_position")]
+    pub position: BarPosition,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct Settings {
     keys: HashMap<String, Key>,
     background: String,
+    #[serde(default = "default_mouse_modifier")]
+    mouse_modifier: String,
+    #[serde(default = "default_activation_policy")]
+    activation_policy: ActivationPolicy,
     layout_manager: LayoutManagerSettings,
     bar: BarSettings,
+    #[serde(default)]
+    rules: Vec<Rule>,
+    // Actions of clicks on the title and status of the bar, bound like "M4-Button1"
+    #[serde(default)]
+    title_buttons: HashMap<String, Key>,
+    #[serde(default)]
+    status_buttons: HashMap<String, Key>,
 }
 
 impl Settings {
     pub fn from_config(config: config::Config) -> Settings {
         match config.try_into() {
             Ok(settings) => {
-                let settings: Settings = settings; // TODO: Better way to give type annotation
+                let mut settings: Settings = settings; // TODO: Better way to give type annotation
+
+                // Keep the ratio in the range IncMasterRatio allows
+                let ratio = &mut settings.layout_manager.master_ratio;
+                *ratio = ratio.clamp(0.05, 0.95);
+                for rule in &mut settings.rules {
+                    if let Err(e) = rule.compile() {
+                        println!("Invalid title regex in rule: {}", e);
+                        process::exit(0)
+                    }
+                }
                 for (key, _) in settings.get_keys() {
                     info!("Map KEY: {}", key);
                 }
@@ -65,10 +186,25 @@ impl Settings {
     pub fn get_keys(&self) -> &HashMap<String, Key> {
         &self.keys
     }
+    pub fn get_mouse_modifier(&self) -> &str {
+        &self.mouse_modifier
+    }
+    pub fn get_activation_policy(&self) -> ActivationPolicy {
+        self.activation_policy
+    }
     pub fn get_layout_manager_settings(&self) -> &LayoutManagerSettings {
         &self.layout_manager
     }
     pub fn get_bar(&self) -> &BarSettings {
         &self.bar
     }
+    pub fn get_rules(&self) -> &Vec<Rule> {
+        &self.rules
+    }
+    pub fn get_title_buttons(&self) -> &HashMap<String, Key> {
+        &self.title_buttons
+    }
+    pub fn get_status_buttons(&self) -> &HashMap<String, Key> {
+        &self.status_buttons
+    }
 }
@@ -0,0 +1,90 @@
+macro_rules! atoms {
+    ($($field:ident => $name:expr,)*) => {
+        pub struct Atoms {
+            $(pub $field: xcb::Atom,)*
+        }
+
+        impl Atoms {
+            pub fn new(connection: &xcb::Connection) -> Atoms {
+                // Send all requests before waiting for any reply
+                $(let $field = xcb::intern_atom(connection, false, $name);)*
+                Atoms {
+                    $($field: $field.get_reply().unwrap().atom(),)*
+                }
+            }
+        }
+    };
+}
+
+atoms! {
+    wm_protocols => "WM_PROTOCOLS",
+    wm_delete_window => "WM_DELETE_WINDOW",
+    wm_state => "WM_STATE",
+    wm_window_role => "WM_WINDOW_ROLE",
+    oscillator_status => "_OSCILLATOR_STATUS",
+    utf8_string => "UTF8_STRING",
+    net_supported => "_NET_SUPPORTED",
+    net_supporting_wm_check => "_NET_SUPPORTING_WM_CHECK",
+    net_wm_name => "_NET_WM_NAME",
+    net_client_list => "_NET_CLIENT_LIST",
+    net_client_list_stacking => "_NET_CLIENT_LIST_STACKING",
+    net_number_of_desktops => "_NET_NUMBER_OF_DESKTOPS",
+    net_desktop_names => "_NET_DESKTOP_NAMES",
+    net_current_desktop => "_NET_CURRENT_DESKTOP",
+    net_wm_desktop => "_NET_WM_DESKTOP",
+    net_active_window => "_NET_ACTIVE_WINDOW",
+    net_wm_state => "_NET_WM_STATE",
+    net_wm_state_fullscreen => "_NET_WM_STATE_FULLSCREEN",
+    net_wm_state_demands_attention => "_NET_WM_STATE_DEMANDS_ATTENTION",
+    net_workarea => "_NET_WORKAREA",
+    net_wm_strut => "_NET_WM_STRUT",
+    net_wm_strut_partial => "_NET_WM_STRUT_PARTIAL",
+    net_wm_window_type => "_NET_WM_WINDOW_TYPE",
+    net_wm_window_type_desktop => "_NET_WM_WINDOW_TYPE_DESKTOP",
+    net_wm_window_type_dock => "_NET_WM_WINDOW_TYPE_DOCK",
+    net_wm_window_type_dialog => "_NET_WM_WINDOW_TYPE_DIALOG",
+    net_wm_window_type_utility => "_NET_WM_WINDOW_TYPE_UTILITY",
+    net_wm_window_type_splash => "_NET_WM_WINDOW_TYPE_SPLASH",
+    net_wm_window_type_notification => "_NET_WM_WINDOW_TYPE_NOTIFICATION",
+    net_wm_window_type_tooltip => "_NET_WM_WINDOW_TYPE_TOOLTIP",
+    net_wm_window_type_popup_menu => "_NET_WM_WINDOW_TYPE_POPUP_MENU",
+    net_wm_window_type_dropdown_menu => "_NET_WM_WINDOW_TYPE_DROPDOWN_MENU",
+    net_wm_window_type_combo => "_NET_WM_WINDOW_TYPE_COMBO",
+    net_wm_window_type_dnd => "_NET_WM_WINDOW_TYPE_DND",
+}
+
+impl Atoms {
+    // EWMH hints advertised in _NET_SUPPORTED
+    pub fn supported(&self) -> Vec<xcb::Atom> {
+        vec![
+            self.net_supported,
+            self.net_supporting_wm_check,
+            self.net_wm_name,
+            self.net_client_list,
+            self.net_client_list_stacking,
+            self.net_number_of_desktops,
+            self.net_desktop_names,
+            self.net_current_desktop,
+            self.net_wm_desktop,
+            self.net_active_window,
+            self.net_wm_state,
+            self.net_wm_state_fullscreen,
+            self.net_wm_state_demands_attention,
+            self.net_workarea,
+            self.net_wm_strut,
+            self.net_wm_strut_partial,
+            self.net_wm_window_type,
+            self.net_wm_window_type_desktop,
+            self.net_wm_window_type_dock,
+            self.net_wm_window_type_dialog,
+            self.net_wm_window_type_utility,
+            self.net_wm_window_type_splash,
+            self.net_wm_window_type_notification,
+            self.net_wm_window_type_tooltip,
+            self.net_wm_window_type_popup_menu,
+            self.net_wm_window_type_dropdown_menu,
+            self.net_wm_window_type_combo,
+            self.net_wm_window_type_dnd,
+        ]
+    }
+}
@@ -1,2 +1,5 @@
+pub mod atom;
 pub mod color;
 pub mod font;
+pub mod size_hints;
+pub mod strut;
@@ -0,0 +1,116 @@
+// Flags of WM_NORMAL_HINTS in ICCCM
+const P_MIN_SIZE: u32 = 1 << 4;
+const P_MAX_SIZE: u32 = 1 << 5;
+const P_RESIZE_INC: u32 = 1 << 6;
+const P_ASPECT: u32 = 1 << 7;
+const P_BASE_SIZE: u32 = 1 << 8;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SizeHints {
+    pub min_width: u32,
+    pub min_height: u32,
+    // 0 means there is no limit
+    pub max_width: u32,
+    pub max_height: u32,
+    pub width_inc: u32,
+    pub height_inc: u32,
+    pub base_width: u32,
+    pub base_height: u32,
+    pub min_aspect: Option<f32>,
+    pub max_aspect: Option<f32>,
+}
+
+impl SizeHints {
+    // Parse the raw WM_SIZE_HINTS property
+    pub fn from_raw(raw: &[u32]) -> SizeHints {
+        let mut hints = SizeHints::default();
+        if raw.len() < 15 {
+            return hints;
+        }
+        let flags = raw[0];
+        let field = |index: usize| raw[index] as i32;
+
+        if flags & P_MIN_SIZE > 0 {
+            hints.min_width = field(5).max(0) as u32;
+            hints.min_height = field(6).max(0) as u32;
+        }
+        if flags & P_MAX_SIZE > 0 {
+            hints.max_width = field(7).max(0) as u32;
+            hints.max_height = field(8).max(0) as u32;
+        }
+        if flags & P_RESIZE_INC > 0 {
+            hints.width_inc = field(9).max(0) as u32;
+            hints.height_inc = field(10).max(0) as u32;
+        }
+        if flags & P_ASPECT > 0 && field(12) > 0 && field(14) > 0 {
+            hints.min_aspect = Some(field(11) as f32 / field(12) as f32);
+            hints.max_aspect = Some(field(13) as f32 / field(14) as f32);
+        }
+        if flags & P_BASE_SIZE > 0 && raw.len() >= 17 {
+            hints.base_width = field(15).max(0) as u32;
+            hints.base_height = field(16).max(0) as u32;
+        }
+
+        // ICCCM: base size and min size stand in for each other
+        if flags & P_BASE_SIZE == 0 {
+            hints.base_width = hints.min_width;
+            hints.base_height = hints.min_height;
+        }
+        if flags & P_MIN_SIZE == 0 {
+            hints.min_width = hints.base_width;
+            hints.min_height = hints.base_height;
+        }
+        hints
+    }
+
+    pub fn is_fixed(&self) -> bool {
+        self.max_width > 0
+            && self.max_height > 0
+            && self.max_width == self.min_width
+            && self.max_height == self.min_height
+    }
+
+    // Shrink the size to one the client accepts
+    pub fn apply(&self, width: u32, height: u32) -> (u32, u32) {
+        let mut width = width as f32;
+        let mut height = height as f32;
+
+        // Aspect ratio is calculated without the base size
+        let base_width = self.base_width as f32;
+        let base_height = self.base_height as f32;
+        if self.min_aspect.is_some() || self.max_aspect.is_some() {
+            let aspect_width = (width - base_width).max(1.0);
+            let aspect_height = (height - base_height).max(1.0);
+            if let Some(max_aspect) = self.max_aspect {
+                if max_aspect > 0.0 && aspect_width / aspect_height > max_aspect {
+                    width = aspect_height * max_aspect + base_width;
+                }
+            }
+            if let Some(min_aspect) = self.min_aspect {
+                if min_aspect > 0.0 && aspect_width / aspect_height < min_aspect {
+                    height = aspect_width / min_aspect + base_height;
+                }
+            }
+        }
+
+        let mut width = width as u32;
+        let mut height = height as u32;
+        if self.width_inc > 0 && width > self.base_width {
+            width -= (width - self.base_width) % self.width_inc;
+        }
+        if self.height_inc > 0 && height > self.base_height {
+            height -= (height - self.base_height) % self.height_inc;
+        }
+
+        width = width.max(self.min_width);
+        height = height.max(self.min_height);
+        if self.max_width > 0 {
+            width = width.min(self.max_width);
+        }
+        if self.max_height > 0 {
+            height = height.min(self.max_height);
+        }
+
+        (width.max(1), height.max(1))
+    }
+}
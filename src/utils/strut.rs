@@ -0,0 +1,132 @@
+use crate::layout_manager::Geometry;
+
+// Space reserved at the edges of an area
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Strut {
+    pub left: u32,
+    pub right: u32,
+    pub top: u32,
+    pub bottom: u32,
+}
+
+impl Strut {
+    // Keep the larger reservation of each edge
+    pub fn merge(&self, other: &Strut) -> Strut {
+        Strut {
+            left: self.left.max(other.left),
+            right: self.right.max(other.right),
+            top: self.top.max(other.top),
+            bottom: self.bottom.max(other.bottom),
+        }
+    }
+}
+
+// _NET_WM_STRUT_PARTIAL: reservations at the edges of the screen, and the range they cover
+#[derive(Clone, Copy, Debug)]
+pub struct PartialStrut {
+    pub strut: Strut,
+    pub left_start_y: u32,
+    pub left_end_y: u32,
+    pub right_start_y: u32,
+    pub right_end_y: u32,
+    pub top_start_x: u32,
+    pub top_end_x: u32,
+    pub bottom_start_x: u32,
+    pub bottom_end_x: u32,
+}
+
+impl PartialStrut {
+    // A strut covering the whole edges, like _NET_WM_STRUT
+    pub fn full(strut: Strut) -> PartialStrut {
+        PartialStrut {
+            strut,
+            left_start_y: 0,
+            left_end_y: u32::MAX,
+            right_start_y: 0,
+            right_end_y: u32::MAX,
+            top_start_x: 0,
+            top_end_x: u32::MAX,
+            bottom_start_x: 0,
+            bottom_end_x: u32::MAX,
+        }
+    }
+
+    // Parse _NET_WM_STRUT_PARTIAL (12 values) or _NET_WM_STRUT (4 values)
+    pub fn from_raw(raw: &[u32]) -> Option<PartialStrut> {
+        if raw.len() < 4 {
+            return None;
+        }
+        let strut = Strut {
+            left: raw[0],
+            right: raw[1],
+            top: raw[2],
+            bottom: raw[3],
+        };
+        if raw.len() < 12 {
+            return Some(PartialStrut::full(strut));
+        }
+        Some(PartialStrut {
+            strut,
+            left_start_y: raw[4],
+            left_end_y: raw[5],
+            right_start_y: raw[6],
+            right_end_y: raw[7],
+            top_start_x: raw[8],
+            top_end_x: raw[9],
+            bottom_start_x: raw[10],
+            bottom_end_x: raw[11],
+        })
+    }
+
+    // Values of _NET_WM_STRUT_PARTIAL
+    pub fn to_raw(self) -> [u32; 12] {
+        [
+            self.strut.left,
+            self.strut.right,
+            self.strut.top,
+            self.strut.bottom,
+            self.left_start_y,
+            self.left_end_y,
+            self.right_start_y,
+            self.right_end_y,
+            self.top_start_x,
+            self.top_end_x,
+            self.bottom_start_x,
+            self.bottom_end_x,
+        ]
+    }
+
+    // The part of the reservation falling into `area` of a screen with the given size
+    pub fn on_area(&self, area: Geometry, screen_width: u32, screen_height: u32) -> Strut {
+        let overlap = |start: u32, end: u32, area_start: u32, area_length: u32| {
+            start < area_start + area_length && end >= area_start
+        };
+        let area_right = area.x + area.width;
+        let area_bottom = area.y + area.height;
+
+        let mut strut = Strut::default();
+        if self.strut.left > 0 && overlap(self.left_start_y, self.left_end_y, area.y, area.height) {
+            strut.left = self.strut.left.saturating_sub(area.x);
+        }
+        if self.strut.right > 0
+            && overlap(self.right_start_y, self.right_end_y, area.y, area.height)
+        {
+            strut.right = area_right.saturating_sub(screen_width.saturating_sub(self.strut.right));
+        }
+        if self.strut.top > 0 && overlap(self.top_start_x, self.top_end_x, area.x, area.width) {
+            strut.top = self.strut.top.saturating_sub(area.y);
+        }
+        if self.strut.bottom > 0
+            && overlap(self.bottom_start_x, self.bottom_end_x, area.x, area.width)
+        {
+            strut.bottom =
+                area_bottom.saturating_sub(screen_height.saturating_sub(self.strut.bottom));
+        }
+        Strut {
+            left: strut.left.min(area.width),
+            right: strut.right.min(area.width),
+            top: strut.top.min(area.height),
+            bottom: strut.bottom.min(area.height),
+        }
+    }
+}